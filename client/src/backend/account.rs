@@ -103,3 +103,14 @@ async fn write_pwd_on_file(psw: String, lg: String) -> Result<()> {
 
     Ok(())
 }
+
+/// Login of the logged in user, if any.
+pub fn current_login() -> Option<String> {
+    USER.get().cloned().flatten().and_then(|user| user.login)
+}
+
+/// Login and password of the logged in user, if any.
+pub fn current_credentials() -> Option<(String, String)> {
+    let user = USER.get().cloned().flatten()?;
+    Some((user.login?, user.password?))
+}
@@ -48,9 +48,18 @@ static THEME_SET: Lazy<ThemeSet> = Lazy::new(|| {
     theme_set
 });
 
+// syntaxes missing from syntect's defaults (assembly, LLVM IR)
+static SYNTAX_PATH: Lazy<PathBuf> = Lazy::new(|| {
+    let root = current_dir().unwrap_or_default();
+    RelativePath::new(&"/assets/syntaxes/").to_path(&root)
+});
+
 static SS: Lazy<SyntaxSet> = Lazy::new(|| {
-    let ss = SyntaxSet::load_defaults_newlines();
-    ss
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    builder
+        .add_from_folder(SYNTAX_PATH.as_path(), true)
+        .expect("Failed to load custom syntaxes");
+    builder.build()
 });
 
 static SYNTAX: Lazy<SyntaxReference> = Lazy::new(|| {
@@ -69,6 +78,15 @@ static C: Lazy<Color> = Lazy::new(|| {
     c
 });
 
+/// Highlights `code` as the files with `extension` (plain text if no syntax
+/// knows it), as a `<pre>` with inline styles.
+pub fn highlight_code(code: &str, extension: &str) -> Result<String, syntect::Error> {
+    let syntax = SS
+        .find_syntax_by_extension(extension)
+        .unwrap_or_else(|| SS.find_syntax_plain_text());
+    highlighted_html_for_string(code, &SS, syntax, &THEME)
+}
+
 //#[server]
 pub async fn get_chapters() -> Result<Vec<Chapter>, ServerFnError> {
     let reading_dir = std::fs::read_dir(GLOSSARY_PATH)?;
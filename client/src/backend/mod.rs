@@ -3,6 +3,7 @@ pub mod db;
 pub mod glossary;
 pub mod learning_level;
 pub mod playground_client;
-pub mod playground_lib;
+pub mod session;
+pub mod transport;
 
 pub use db::get_db;
@@ -1,100 +1,102 @@
-use std::{
-    io::{self, ErrorKind},
-    net::TcpStream
-};
+use playground_protocol::*;
 
-use crate::backend::playground_lib::*;
+use crate::backend::session;
+use crate::backend::transport::{self, Transport};
 
-
-
-pub struct TcpClient {
-    pub stream: Option<TcpStream>,
+/// Client of the playground server, over the transport of the platform.
+pub struct PlaygroundClient {
+    pub transport: Option<Box<dyn Transport>>,
 }
 
-impl TcpClient {
+impl PlaygroundClient {
     pub fn init_as_none() -> Self {
-        TcpClient { stream: None }
+        PlaygroundClient { transport: None }
     }
 
     pub fn shutdown(&mut self) {
-        if let Some(stream) = self.stream.as_mut() {
-            let _ = write_json_info(stream, JsonInfo::from("shutdown", ""));
-            let _ = stream.shutdown(std::net::Shutdown::Both);
+        if let Some(transport) = self.transport.as_mut() {
+            let _ = transport.send(&ClientMessage::Exit);
+            transport.close();
         }
-        self.stream = None;
+        self.transport = None;
     }
 
-    pub fn spawn(addr: &str) -> Result<Self, ()> {
-        match Self::spawn_stream(addr) {
-            Ok(stream) => Ok(TcpClient { stream: Some(stream) }),
-            Err(err) => Err(err),
-        }
+    pub async fn spawn(addr: &str) -> Result<Self, ()> {
+        let hello = match session::session_token().await {
+            Some(token) => ClientMessage::hello_with_token(token),
+            None => ClientMessage::hello(),
+        };
+        Ok(PlaygroundClient { transport: Some(transport::connect(addr, &hello)?) })
     }
 
-    fn spawn_stream(addr: &str) -> Result<TcpStream, ()> {
-        let stream = TcpStream::connect(addr);
-
-        if stream.is_err() {
-            return Err(());
+    fn send(&mut self, msg: &ClientMessage) {
+        if let Some(transport) = self.transport.as_mut() {
+            let _ = transport.send(msg);
         }
-        
-        let stream = stream.expect(format!("Error: Trying to connect to {addr} failed").as_str());
-
-        if stream.set_nonblocking(true).is_err() {
-            return Err(());
-        }
-
-        Ok(stream)
     }
 
-    pub fn read(&mut self) -> Result<Option<JsonInfo>, Box<dyn std::error::Error>> {
-        if let Some(tcpclient) = self.stream.as_mut() {
-            if let Ok(mut stream) = tcpclient.try_clone() {
-                match read_json_info(&mut stream) {
-                    Ok(info) => {
-                        match info.header.as_str() {
-                            "request_corrupted" => Ok(None),
-                            _ => Ok(Some(info)),
-                        }
-                    },
-                    Err(e) => {
-                        if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                            match e.kind() {
-                                ErrorKind::WouldBlock => {//non-block error
-                                    Ok(None)
-                                },
-                                _ => {//severe error
-                                    Err(format!("Client error while listening: {:?}", e).into())
-                                },
-                            }
-                        } else {//non severe error
-                            Ok(None)
-                        }
-                    }
-                }
-            } else {
-                Err(format!("TcpClient could not be accessed").into())
+    pub fn read(&mut self) -> Result<Option<ServerMessage>, Box<dyn std::error::Error>> {
+        if let Some(transport) = self.transport.as_mut() {
+            match transport.try_recv() {
+                Ok(Some(ServerMessage::RequestCorrupted { reason })) => {
+                    log::warn!("Server could not parse our request: {reason}");
+                    Ok(None)
+                },
+                Ok(Some(ServerMessage::Welcome { protocol_version, user })) => {
+                    log::debug!("Connected to playground server (protocol v{protocol_version}, user {user:?})");
+                    Ok(None)
+                },
+                Ok(msg) => Ok(msg),
+                Err(e) if e.is_fatal() => {//severe error
+                    Err(format!("Client error while listening: {:?}", e).into())
+                },
+                Err(e) => {//non severe error (e.g. unknown message type)
+                    log::warn!("Discarding server message: {e}");
+                    Ok(None)
+                },
             }
         } else {
-            Err(format!("TcpClient was not initialized").into())
+            Err("PlaygroundClient was not initialized".into())
         }
     }
 
-    pub fn send_run_compile_req(&mut self, req: String) {
-        if let Some(stream) = self.stream.as_mut() {
-            let _r = write_json_info(
-                stream,
-                JsonInfo::from("run&compile", &req
-            ));
-        }
+    pub fn send_run_compile_req(&mut self, project: Project, toolchain: Toolchain, build: BuildOptions) {
+        self.send(&ClientMessage::RunCompile { project, toolchain, build, limits: RunLimits::default() });
+    }
+
+    pub fn send_command_req(&mut self, command: CargoCommand, project: Project, toolchain: Toolchain) {
+        self.send(&ClientMessage::Command { command, project, toolchain, limits: RunLimits::default() });
+    }
+
+    pub fn send_test_req(&mut self, project: Project, toolchain: Toolchain) {
+        self.send(&ClientMessage::Test { project, toolchain, limits: RunLimits::default() });
+    }
+
+    pub fn send_lint_req(&mut self, project: Project, toolchain: Toolchain) {
+        self.send(&ClientMessage::Lint { project, toolchain });
+    }
+
+    pub fn send_inspect_req(&mut self, project: Project, target: InspectTarget, toolchain: Toolchain, build: BuildOptions) {
+        self.send(&ClientMessage::Inspect { project, target, toolchain, build });
+    }
+
+    pub fn send_format_req(&mut self, source: String, edition: Edition) {
+        self.send(&ClientMessage::Format { source, edition });
+    }
+
+    pub fn send_share_req(&mut self, snippet: Snippet) {
+        self.send(&ClientMessage::Share { snippet });
+    }
+
+    pub fn send_load_snippet_req(&mut self, id: String) {
+        self.send(&ClientMessage::LoadSnippet { id });
+    }
+
+    pub fn send_list_crates_req(&mut self) {
+        self.send(&ClientMessage::ListCrates);
     }
 
     pub fn send_input_req(&mut self, req: String) {
-        if let Some(stream) = self.stream.as_mut() {
-            let _ = write_json_info(
-                stream,
-                JsonInfo::from("input", &req)
-            );
-        }
+        self.send(&ClientMessage::Input { data: req });
     }
-}
\ No newline at end of file
+}
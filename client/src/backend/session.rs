@@ -0,0 +1,67 @@
+use dioxus::prelude::*;
+
+use crate::backend::account;
+
+/// How long the playground server accepts a session token.
+#[cfg(feature = "server")]
+const SESSION_TOKEN_LIFETIME: std::time::Duration = std::time::Duration::from_secs(12 * 60 * 60);
+
+/// File with the key shared with the playground server. Read by the server
+/// of the app only: the key must never reach the clients, or anyone could
+/// sign a token for any account.
+#[cfg(feature = "server")]
+const AUTH_KEY_FILE_VAR: &str = "PLAYGROUND_AUTH_KEY_FILE";
+
+/// Token tying the playground sessions to the logged in user, issued by the
+/// server of the app. `None` if nobody is logged in or no token could be issued.
+pub async fn session_token() -> Option<String> {
+    let (login, password) = account::current_credentials()?;
+
+    match issue_session_token(login, password).await {
+        Ok(token) => token,
+        Err(e) => {
+            log::warn!("No session token for the playground: {e}");
+            None
+        },
+    }
+}
+
+/// Signs a session token for the account of `login`, once its credentials
+/// are checked against the accounts; `None` if the playground server doesn't
+/// tie the sessions to accounts (no key).
+///
+/// The role (e.g. `teacher`), which picks the limits of the playground, is the
+/// one of the account: never one the client asks for.
+#[server(IssueSessionToken)]
+async fn issue_session_token(login: String, password: String) -> Result<Option<String>, ServerFnError> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use playground_protocol::{sign_token, SessionClaims};
+
+    use crate::backend::get_db;
+
+    #[derive(serde::Deserialize)]
+    struct Account {
+        login: String,
+        role: Option<String>,
+    }
+
+    let Ok(key_file) = std::env::var(AUTH_KEY_FILE_VAR) else {
+        return Ok(None);
+    };
+    let key = std::fs::read(&key_file).map_err(|e| ServerFnError::new(format!("{key_file}: {e}")))?;
+
+    let mut res = get_db()
+        .await
+        .query("SELECT login, role FROM user WHERE login = $login AND password = $password")
+        .bind(("login", login))
+        .bind(("password", password))
+        .await
+        .map_err(ServerFnError::new)?;
+    let Some(account) = res.take::<Option<Account>>(0).map_err(ServerFnError::new)? else {
+        return Err(ServerFnError::new("not logged in"));
+    };
+
+    let expires_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() + SESSION_TOKEN_LIFETIME.as_secs();
+    Ok(Some(sign_token(&SessionClaims { login: account.login, role: account.role, expires_at }, key.trim_ascii())))
+}
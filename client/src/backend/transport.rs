@@ -0,0 +1,285 @@
+use playground_protocol::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tcp::TcpTransport;
+#[cfg(target_arch = "wasm32")]
+pub use websocket::WebSocketTransport;
+
+/// Where the playground server is: its TCP address on desktop...
+#[cfg(not(target_arch = "wasm32"))]
+pub const SERVER_ADDRESS: &str = "127.0.0.1:8000";
+/// ...and its WebSocket URL in the browser (`wss://` if it serves TLS).
+#[cfg(target_arch = "wasm32")]
+pub const SERVER_ADDRESS: &str = "ws://127.0.0.1:8001/";
+
+/// Carries the protocol messages to the playground server and back: TCP on
+/// desktop, WebSocket in the browser, where sockets can't be opened.
+pub trait Transport {
+    fn send(&mut self, msg: &ClientMessage) -> Result<(), ProtocolError>;
+
+    /// The next message of the server, if one arrived; never blocks.
+    fn try_recv(&mut self) -> Result<Option<ServerMessage>, ProtocolError>;
+
+    fn close(&mut self);
+}
+
+/// Connects to the server at `addr` with the transport of this platform,
+/// greeting it with `hello`.
+pub fn connect(addr: &str, hello: &ClientMessage) -> Result<Box<dyn Transport>, ()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Ok(Box::new(TcpTransport::connect(addr, hello)?));
+    #[cfg(target_arch = "wasm32")]
+    return Ok(Box::new(WebSocketTransport::connect(addr, hello)?));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tcp {
+    use std::io::{self, Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+
+    use playground_protocol::*;
+    use rustls::pki_types::{pem::PemObject, CertificateDer, ServerName};
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+    use super::Transport;
+
+    /// PEM file of the CA the server certificate is checked with: when set, the
+    /// server is reached over TLS.
+    const TLS_CA_FILE_VAR: &str = "PLAYGROUND_TLS_CA_FILE";
+
+    /// Stream to the server: plain TCP for local development, or TLS over it.
+    pub enum ServerStream {
+        Plain(TcpStream),
+        Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+    }
+
+    impl ServerStream {
+        fn tcp(&self) -> &TcpStream {
+            match self {
+                ServerStream::Plain(stream) => stream,
+                ServerStream::Tls(stream) => stream.get_ref(),
+            }
+        }
+    }
+
+    impl Read for ServerStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self {
+                ServerStream::Plain(stream) => stream.read(buf),
+                ServerStream::Tls(stream) => stream.read(buf),
+            }
+        }
+    }
+
+    impl Write for ServerStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self {
+                ServerStream::Plain(stream) => stream.write(buf),
+                ServerStream::Tls(stream) => stream.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self {
+                ServerStream::Plain(stream) => stream.flush(),
+                ServerStream::Tls(stream) => stream.flush(),
+            }
+        }
+    }
+
+    /// TLS settings trusting the CA of [`TLS_CA_FILE_VAR`], or `None` if it is not set.
+    fn tls_config() -> Result<Option<Arc<ClientConfig>>, String> {
+        let Ok(ca_file) = std::env::var(TLS_CA_FILE_VAR) else {
+            return Ok(None);
+        };
+
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(&ca_file).map_err(|e| format!("{ca_file}: {e}"))? {
+            roots.add(cert.map_err(|e| format!("{ca_file}: {e}"))?).map_err(|e| format!("{ca_file}: {e}"))?;
+        }
+
+        let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Some(Arc::new(config)))
+    }
+
+    /// Length-prefixed frames over a non-blocking socket.
+    pub struct TcpTransport {
+        reader: MessageReader<ServerStream>,
+    }
+
+    impl TcpTransport {
+        pub fn connect(addr: &str, hello: &ClientMessage) -> Result<Self, ()> {
+            let stream = TcpStream::connect(addr);
+
+            if stream.is_err() {
+                return Err(());
+            }
+
+            let stream = stream.expect(format!("Error: Trying to connect to {addr} failed").as_str());
+
+            //TLS if a CA is configured, never plain TCP instead
+            let mut stream = match tls_config() {
+                Ok(Some(config)) => {
+                    let host = addr.rsplit_once(':').map_or(addr, |(host, _port)| host);
+                    let Ok(name) = ServerName::try_from(host.to_string()) else {
+                        return Err(());
+                    };
+                    let Ok(conn) = ClientConnection::new(config, name) else {
+                        return Err(());
+                    };
+                    ServerStream::Tls(Box::new(StreamOwned::new(conn, stream)))
+                },
+                Ok(None) => ServerStream::Plain(stream),
+                Err(err) => {
+                    log::error!("Invalid {TLS_CA_FILE_VAR}: {err}");
+                    return Err(());
+                },
+            };
+
+            //handshake first (TLS one included), while the socket is still blocking
+            if write_message(&mut stream, hello).is_err() {
+                return Err(());
+            }
+
+            if stream.tcp().set_nonblocking(true).is_err() {
+                return Err(());
+            }
+
+            Ok(TcpTransport { reader: MessageReader::new(stream) })
+        }
+    }
+
+    impl Transport for TcpTransport {
+        fn send(&mut self, msg: &ClientMessage) -> Result<(), ProtocolError> {
+            write_message(self.reader.get_mut(), msg)
+        }
+
+        fn try_recv(&mut self) -> Result<Option<ServerMessage>, ProtocolError> {
+            self.reader.try_read()
+        }
+
+        fn close(&mut self) {
+            let _ = self.reader.get_mut().tcp().shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod websocket {
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::rc::Rc;
+
+    use playground_protocol::*;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{Event, MessageEvent, WebSocket};
+
+    use super::Transport;
+
+    /// The browser's WebSocket, one JSON message per WebSocket message.
+    ///
+    /// The browser calls back on every message: they wait in a queue until
+    /// [`Transport::try_recv`] is called.
+    pub struct WebSocketTransport {
+        socket: WebSocket,
+        incoming: Rc<RefCell<VecDeque<String>>>,
+        outgoing: Rc<RefCell<Vec<String>>>,//sent once open
+        closed: Rc<Cell<bool>>,
+        _callbacks: [Closure<dyn FnMut(Event)>; 4],//alive as long as the socket
+    }
+
+    impl WebSocketTransport {
+        pub fn connect(url: &str, hello: &ClientMessage) -> Result<Self, ()> {
+            let socket = WebSocket::new(url).map_err(|_| ())?;
+            let incoming = Rc::new(RefCell::new(VecDeque::new()));
+            let outgoing = Rc::new(RefCell::new(Vec::<String>::new()));
+            let closed = Rc::new(Cell::new(false));
+
+            let on_open = {
+                let (socket, outgoing) = (socket.clone(), Rc::clone(&outgoing));
+                Closure::<dyn FnMut(Event)>::new(move |_| {
+                    for json in outgoing.borrow_mut().drain(..) {
+                        let _ = socket.send_with_str(&json);
+                    }
+                })
+            };
+            let on_message = {
+                let incoming = Rc::clone(&incoming);
+                Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+                    let text = event.dyn_into::<MessageEvent>().ok().and_then(|event| event.data().as_string());
+                    if let Some(json) = text {
+                        incoming.borrow_mut().push_back(json);
+                    }
+                })
+            };
+            let on_close = {
+                let closed = Rc::clone(&closed);
+                Closure::<dyn FnMut(Event)>::new(move |_| closed.set(true))
+            };
+            let on_error = {
+                let closed = Rc::clone(&closed);
+                Closure::<dyn FnMut(Event)>::new(move |_| closed.set(true))
+            };
+
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+            socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+            let mut transport = WebSocketTransport {
+                socket,
+                incoming,
+                outgoing,
+                closed,
+                _callbacks: [on_open, on_message, on_close, on_error],
+            };
+            transport.send(hello).map_err(|_| ())?;
+            Ok(transport)
+        }
+    }
+
+    impl Transport for WebSocketTransport {
+        fn send(&mut self, msg: &ClientMessage) -> Result<(), ProtocolError> {
+            let json = serde_json::to_string(msg)?;
+            if json.len() > MAX_FRAME_LEN {
+                return Err(ProtocolError::FrameTooLarge { len: json.len(), max: MAX_FRAME_LEN });
+            }
+
+            match self.socket.ready_state() {
+                WebSocket::CONNECTING => self.outgoing.borrow_mut().push(json),
+                WebSocket::OPEN => self.socket.send_with_str(&json)
+                    .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "WebSocket send failed"))?,
+                _ => return Err(io::Error::from(io::ErrorKind::NotConnected).into()),
+            }
+            Ok(())
+        }
+
+        fn try_recv(&mut self) -> Result<Option<ServerMessage>, ProtocolError> {
+            match self.incoming.borrow_mut().pop_front() {
+                Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+                None if self.closed.get() => Err(io::Error::from(io::ErrorKind::ConnectionAborted).into()),
+                None => Ok(None),
+            }
+        }
+
+        fn close(&mut self) {
+            let _ = self.socket.close();
+        }
+    }
+
+    impl Drop for WebSocketTransport {
+        fn drop(&mut self) {
+            //the callbacks are freed with the transport
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            self.socket.set_onerror(None);
+        }
+    }
+}
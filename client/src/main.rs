@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 
 use crate::backend::learning_level::*;
 use crate::components::Navbar;
-use crate::views::{Account, Glossary, Home, Learning, Login, Signup, Playground};
+use crate::views::{Account, Glossary, Home, Learning, Login, Signup, Playground, SharedPlayground};
 
 mod backend;
 mod components;
@@ -32,6 +32,9 @@ enum Route {
 
     #[route("/playground")]
     Playground {},
+
+    #[route("/playground/:id")]
+    SharedPlayground { id: String },
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -17,4 +17,4 @@ mod learning;
 pub use learning::Learning;
 
 mod playground;
-pub use playground::Playground;
\ No newline at end of file
+pub use playground::{Playground, SharedPlayground};
\ No newline at end of file
@@ -3,9 +3,10 @@ use dioxus::{
     prelude::*
 };
 
-use crate::backend::{
-    playground_client::TcpClient,
-    playground_lib::*
+use crate::backend::{account::current_login, glossary::highlight_code, playground_client::PlaygroundClient, transport::SERVER_ADDRESS};
+use crate::Route;
+use playground_protocol::{
+    validate_path, BuildOptions, BuildProfile, CargoCommand, Channel, CrateInfo, Diagnostic, DiagnosticLevel, Edition, InspectTarget, LintItem, OptLevel, Project, ProjectFile, ServerMessage, Snippet, TestResult, TestStatus, TestSummary, Toolchain, MAIN_PATH
 };
 
 use std::time::Duration;
@@ -21,39 +22,134 @@ const DEFAULT_FN: &str = r#"fn main() {
 
 
 
-fn js_append_out(info: JsonInfo) -> Eval {
+/// What the Compile & Run button (and its siblings) ask the server.
+#[derive(Clone, PartialEq)]
+enum Request {
+    RunCompile,
+    Command(CargoCommand),
+    Lint,
+    Test,
+    Inspect(InspectTarget),
+}
+
+fn js_append_out(info: ServerMessage) -> Eval {
     let style: String;
     let classes: String;
     let txt: String;
 
-    match info.header.as_str() {
-        "exit" => {
+    match info {
+        ServerMessage::Exit { reason } => {
             classes = "".to_string();
-            txt = "exit".to_string()+&info.body;
+            txt = "exit".to_string()+&reason;
         },
-        "error" => {
+        ServerMessage::Error { message } => {
             classes = "err".to_string();
-            txt = "----- ERROR -----\n".to_string()+&info.body;
+            txt = "----- ERROR -----\n".to_string()+&message;
         },
-        "stderr" => {
+        ServerMessage::Stderr { data } => {
             classes = "exterr".to_string();
-            txt = info.body;
+            txt = data;
         },
-        "stdout" => {
+        ServerMessage::Stdout { data } => {
             classes = "".to_string();
-            txt = info.body;
+            txt = data;
+        },
+        ServerMessage::Diagnostic { diagnostic } => {
+            classes = match diagnostic.level {
+                DiagnosticLevel::Error | DiagnosticLevel::Ice => "complog err".to_string(),
+                _ => "complog".to_string(),
+            };
+            txt = diagnostic.rendered.clone().unwrap_or_else(|| diagnostic.to_string());
+        },
+        ServerMessage::Formatted { .. } => {
+            classes = "".to_string();
+            txt = "Code formatted".to_string();
+        },
+        ServerMessage::FormatError { message, line, column } => {
+            classes = "err".to_string();
+            txt = match (line, column) {
+                (Some(line), Some(column)) => format!("FORMAT ERROR ----------\n{message} (line {line}, column {column})"),
+                _ => format!("FORMAT ERROR ----------\n{message}"),
+            };
         },
-        "compilation_result" => {
+        ServerMessage::Lint { lint } => {
             classes = "complog".to_string();
-            txt = info.body;
+            txt = lint.to_string();
+        },
+        ServerMessage::CompilationResult { output } => {
+            classes = "complog".to_string();
+            txt = output;
+        },
+        ServerMessage::Queued { position, estimated_wait_secs } => {
+            classes = "".to_string();
+            txt = format!(
+                "WAITING IN QUEUE ----------\nPosition: {position} (about {estimated_wait_secs}s left)"
+            );
+        },
+        ServerMessage::Admitted => {
+            classes = "".to_string();
+            txt = "It's your turn!".to_string();
+        },
+        ServerMessage::InspectOutput { data, .. } => {
+            classes = "".to_string();
+            txt = data;
+        },
+        ServerMessage::InspectFinished { target, truncated } => {
+            classes = "".to_string();
+            txt = match truncated {
+                true => format!("INSPECT ----------\n{target} ready (too large: truncated)"),
+                false => format!("INSPECT ----------\n{target} ready"),
+            };
+        },
+        ServerMessage::TestResult { result } => {
+            classes = match result.status {
+                TestStatus::Failed => "err".to_string(),
+                _ => "".to_string(),
+            };
+            txt = result.to_string();
+        },
+        ServerMessage::TestSummary { summary } => {
+            classes = if summary.failed > 0 { "err".to_string() } else { "".to_string() };
+            txt = format!("TEST RESULT ----------\n{summary}");
+        },
+        ServerMessage::RateLimited { message, retry_after_secs } => {
+            classes = "err".to_string();
+            txt = format!("----- LIMIT REACHED -----\n{message}, try again in {retry_after_secs}s");
+        },
+        ServerMessage::ShuttingDown { grace_period_secs } => {
+            classes = "err".to_string();
+            txt = format!("SERVER SHUTTING DOWN ----------\nThe request in progress is stopped if not over in {grace_period_secs}s");
         },
-        "" => {
+        ServerMessage::RunFinished { reason } => {
+            classes = if reason.is_success() { "".to_string() } else { "err".to_string() };
+            txt = format!("RUN FINISHED ----------\n{reason}");
+        },
+        ServerMessage::Shared { id } => {
             classes = "".to_string();
-            txt = info.body;
+            txt = format!("SHARED ----------\n/playground/{id}");
         },
-        _ => {
+        ServerMessage::Snippet { id, snippet } => {
+            classes = "".to_string();
+            txt = match snippet.author {
+                Some(author) => format!("Snippet {id} loaded (shared by {author})"),
+                None => format!("Snippet {id} loaded"),
+            };
+        },
+        ServerMessage::CrateList { crates } => {
+            classes = "".to_string();
+            txt = format!("AVAILABLE CRATES: {}", crates.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", "));
+        },
+        ServerMessage::Status { message } => {
+            classes = "".to_string();
+            txt = message;
+        },
+        ServerMessage::RequestCorrupted { reason } => {
             classes =  "".to_string();
-            txt = format!("{}:\n{}",info.header.to_uppercase(), &info.body);
+            txt = format!("REQUEST_CORRUPTED:\n{reason}");
+        },
+        ServerMessage::Welcome { protocol_version, user } => {
+            classes =  "".to_string();
+            txt = format!("WELCOME:\nprotocol v{protocol_version}{}", user.map(|user| format!(", {user}")).unwrap_or_default());
         }
     }
 
@@ -92,6 +188,24 @@ fn js_get_in(target: &str) -> Eval {
     )
 }
 
+//select a span of the editor (1-based line & column, like rustc)
+fn js_select_in_editor(line_start: usize, column_start: usize, line_end: usize, column_end: usize) -> Eval {
+    document::eval(
+        &format!(
+            r#"
+                let editor = document.getElementById("playground_file_input");
+                let lines = editor.value.split("\n");
+                let offset = (line, column) => lines.slice(0, line - 1).reduce((sum, l) => sum + l.length + 1, 0) + column - 1;
+                editor.focus();
+                editor.setSelectionRange(offset({line_start}, {column_start}), offset({line_end}, {column_end}));
+                let line_height = parseFloat(getComputedStyle(editor).lineHeight) || 20;
+                editor.scrollTop = Math.max(0, ({line_start} - 3) * line_height);
+                return 'OK';
+            "#,
+        )
+    )
+}
+
 fn js_clear_in(target: &str) -> Eval {
     document::eval(
         &format!(
@@ -106,93 +220,347 @@ fn js_clear_in(target: &str) -> Eval {
 
 #[component]
 pub fn Playground() -> Element {
+    rsx! { Editor {} }
+}
+
+/// The playground opened on the snippet shared as `id`.
+#[component]
+pub fn SharedPlayground(id: String) -> Element {
+    rsx! { Editor { snippet_id: id } }
+}
+
+#[component]
+fn Editor(snippet_id: Option<String>) -> Element {
     let mut run_compile_clicked = use_signal(|| false);//trace run & compile button state
     let mut shutdown_client = use_signal(|| false);
-    let mut client = use_signal(|| TcpClient::init_as_none());
+    let mut client = use_signal(|| PlaygroundClient::init_as_none());
+    let mut project = use_signal(|| Project::single(DEFAULT_FN));
+    let mut selected = use_signal(|| MAIN_PATH.to_string());//path of the file in the editor
+    let mut toolchain = use_signal(Toolchain::default);//channel & edition of the next requests
+    let mut build = use_signal(BuildOptions::default);//profile of the next runs
+    let mut available_crates = use_signal(Vec::<CrateInfo>::new);
+    let mut diagnostics = use_signal(Vec::<Diagnostic>::new);
+    let mut lints = use_signal(Vec::<LintItem>::new);
+    let mut tests = use_signal(Vec::<TestResult>::new);
+    let mut test_summary = use_signal(|| None::<TestSummary>);
+    let mut fixes_stale = use_signal(|| false);//offsets are wrong once a fix is applied
+    let mut inspect_target = use_signal(|| InspectTarget::Asm);
+    let mut inspection = use_signal(|| None::<(InspectTarget, String)>);//highlighted html of the last inspect
+    let mut shared = use_signal(|| None::<String>);//id of the last share
 
-    //define run & compile function
-    let run_compile = move || {
+    //ask the server which crates the project can use
+    use_future(move || async move {
+        let Ok(mut crates_client) = PlaygroundClient::spawn(SERVER_ADDRESS).await else {
+            return;
+        };
+        crates_client.send_list_crates_req();
+
+        loop {
+            match crates_client.read() {
+                Ok(Some(ServerMessage::CrateList { crates })) => {
+                    available_crates.set(crates);
+                    break;
+                },
+                Ok(Some(ServerMessage::Exit { .. })) | Err(_) => break,
+                Ok(_) => task::sleep(Duration::from_millis(200)).await,
+            }
+        }
+
+        crates_client.shutdown();
+    });
+
+    //open the shared snippet in the editor (again when the route moves to another one)
+    let _ = use_resource(use_reactive!(|(snippet_id,)| async move {
+        let Some(id) = snippet_id else {
+            return;
+        };
+        let Ok(mut snippet_client) = PlaygroundClient::spawn(SERVER_ADDRESS).await else {
+            js_append_out(ServerMessage::error("Could not reach the server to load the snippet"));
+            return;
+        };
+        snippet_client.send_load_snippet_req(id);
+
+        loop {
+            match snippet_client.read() {
+                Ok(Some(info @ ServerMessage::Snippet { .. })) => {
+                    if let ServerMessage::Snippet { snippet, .. } = &info {
+                        selected.set(snippet.project.files.first().map_or(MAIN_PATH.to_string(), |file| file.path.clone()));
+                        project.set(snippet.project.clone());
+                        toolchain.set(snippet.toolchain);
+                        build.set(snippet.build);
+                        shared.set(None);
+                    }
+                    if let Err(err) = js_append_out(info).await {
+                        eprintln!("{err}");
+                    }
+                    break;
+                },
+                Ok(Some(info @ ServerMessage::Error { .. })) => {
+                    if let Err(err) = js_append_out(info).await {
+                        eprintln!("{err}");
+                    }
+                    break;
+                },
+                Ok(Some(ServerMessage::Exit { .. })) | Err(_) => break,
+                Ok(_) => task::sleep(Duration::from_millis(200)).await,
+            }
+        }
+
+        snippet_client.shutdown();
+    }));
+
+    //define run & compile function (also used by the cargo commands and the lints)
+    let run_compile = move |kind: Request| {
         spawn(async move {
             match js_clear_in("playground_file_output").await {//clear text
                 Ok(_msg) => {},
                 Err(err) => eprintln!("{err}"),
             }
-            
-            match js_get_in("playground_file_input").await {//get playground_file_input' value
-                Ok(input) => {
-                    match input.as_str() {
-                        Some(input) => {
-                            if let Ok(temp_new_client) = TcpClient::spawn("127.0.0.1:8000") {//spawn client
-                                {
-                                    client.set(temp_new_client);
-                                    client.write().send_run_compile_req(input.to_string());
+            diagnostics.write().clear();
+            lints.write().clear();
+            tests.write().clear();
+            test_summary.set(None);
+            inspection.set(None);
+            fixes_stale.set(false);
+
+            let request = project.read().clone();
+            let toolchain = *toolchain.read();
+            let build = *build.read();
+            if let Err(err) = request.validate() {
+                js_append_out(ServerMessage::error(format!("Could not send the project: {err}")));
+                run_compile_clicked.set(false);
+                return;
+            }
+
+            if let Ok(temp_new_client) = PlaygroundClient::spawn(SERVER_ADDRESS).await {//spawn client
+                {
+                    client.set(temp_new_client);
+                    match kind {
+                        Request::RunCompile => client.write().send_run_compile_req(request, toolchain, build),
+                        Request::Command(command) => client.write().send_command_req(command, request, toolchain),
+                        Request::Lint => client.write().send_lint_req(request, toolchain),
+                        Request::Test => client.write().send_test_req(request, toolchain),
+                        Request::Inspect(target) => client.write().send_inspect_req(request, target, toolchain, build),
+                    }
+                }
+
+                let mut _read_res = Ok(None);
+                let mut inspected = String::new();//chunks of the inspect output
+
+                loop {
+                    { _read_res = client.write().read(); }//read from stream
+                    match _read_res {
+                        Ok(None) => {},
+                        Ok(jsoninfo) => {
+                            if let Some(info) = jsoninfo {
+                                if let ServerMessage::Exit { .. } = info {
+                                    break;
                                 }
-                    
-                                let mut _read_res = Ok(None);
-                                
-                                loop {
-                                    { _read_res = client.write().read(); }//read from stream
-                                    match _read_res {
-                                        Ok(None) => {},
-                                        Ok(jsoninfo) => {
-                                            if let Some(info) = jsoninfo {
-                                                if info.header == "exit" {
-                                                    break;
-                                                }
-                    
-                                                if let Err(err) = js_append_out(info).await {
-                                                    eprintln!("{err}");
-                                                } 
-                                            }
-                                            
-                                        },
-                                        Err(err) => {
-                                            let msg = format!(
-                                                "----- FATAL ERROR -----\n{}", err
-                                            );
 
-                                            if let Err(err) = js_append_out(
-                                                JsonInfo::from_string("error".to_string(), msg)
-                                            ).await {
-                                                eprintln!("{err}");
-                                            }
-                                            break;
-                                        },
+                                //the request was refused, nothing else is coming
+                                if let ServerMessage::RateLimited { .. } = info {
+                                    if let Err(err) = js_append_out(info).await {
+                                        eprintln!("{err}");
                                     }
+                                    { client.write().shutdown(); }
+                                    break;
+                                }
+
+                                if let ServerMessage::Diagnostic { diagnostic } = &info {
+                                    diagnostics.write().push(diagnostic.clone());
+                                }
+
+                                if let ServerMessage::Lint { lint } = info {
+                                    lints.write().push(lint);//shown in their own panel
+                                    continue;
+                                }
+
+                                if let ServerMessage::TestResult { result } = info {
+                                    tests.write().push(result);//shown in their own panel
+                                    continue;
+                                }
+
+                                if let ServerMessage::InspectOutput { data, .. } = info {
+                                    inspected.push_str(&data);//shown in its own pane
+                                    continue;
+                                }
 
-                                    if *shutdown_client.read() {
-                                        { client.write().shutdown(); }
-                                        break;
+                                if let ServerMessage::InspectFinished { target, .. } = &info {
+                                    let extension = match target {
+                                        InspectTarget::Asm => "s",
+                                        InspectTarget::LlvmIr => "ll",
+                                        _ => "rs",
+                                    };
+                                    match highlight_code(&std::mem::take(&mut inspected), extension) {
+                                        Ok(html) => inspection.set(Some((*target, html))),
+                                        Err(err) => eprintln!("{err}"),
                                     }
-                    
-                                    task::sleep(Duration::from_millis(500)).await;
+                                }
+
+                                if let ServerMessage::TestSummary { summary } = &info {
+                                    test_summary.set(Some(*summary));
+                                }
+
+                                if let Err(err) = js_append_out(info).await {
+                                    eprintln!("{err}");
                                 }
                             }
+
                         },
-                        None => {
-                            js_append_out(JsonInfo::from_string(//append text to output
-                                "error".to_string(),
-                                format!(
-                                    "{}{}",
-                                    "Could not send input: ",
-                                    "element with 'playground_file_input' id has a incomprehensible value!"
-                                )
-                            ));
+                        Err(err) => {
+                            let msg = format!(
+                                "----- FATAL ERROR -----\n{}", err
+                            );
+
+                            if let Err(err) = js_append_out(
+                                ServerMessage::error(msg)
+                            ).await {
+                                eprintln!("{err}");
+                            }
+                            break;
                         },
                     }
-                },
-                Err(err) => {
-                    js_append_out(JsonInfo::from_string(//append text to output
-                        "error".to_string(),
-                        format!("Could not send input: {err}")
-                    ));
-                },
-            };
-            
+
+                    if *shutdown_client.read() {
+                        { client.write().shutdown(); }
+                        break;
+                    }
+
+                    task::sleep(Duration::from_millis(500)).await;
+                }
+            }
+
             run_compile_clicked.set(false);//report end request (toggle now in RUN & COMPILE)
         });
     };
 
+    //format the file in the editor, replacing it with rustfmt's output
+    let format_code = move || {
+        spawn(async move {
+            let path = selected.read().clone();
+            let Some(source) = project.read().file(&path).map(|file| file.contents.clone()) else {
+                return;
+            };
+
+            let Ok(mut format_client) = PlaygroundClient::spawn(SERVER_ADDRESS).await else {
+                js_append_out(ServerMessage::error("Could not reach the server to format the code"));
+                return;
+            };
+            format_client.send_format_req(source, toolchain.read().edition);
+
+            loop {
+                match format_client.read() {
+                    Ok(Some(ServerMessage::Formatted { source })) => {
+                        if let Some(file) = project.write().files.iter_mut().find(|file| file.path == path) {
+                            file.contents = source;
+                        }
+                        fixes_stale.set(true);//offsets of the diagnostics changed
+                        break;
+                    },
+                    Ok(Some(info @ (ServerMessage::FormatError { .. } | ServerMessage::Error { .. }))) => {
+                        if let Err(err) = js_append_out(info).await {
+                            eprintln!("{err}");
+                        }
+                        break;
+                    },
+                    Ok(Some(ServerMessage::Exit { .. })) | Err(_) => break,
+                    Ok(_) => task::sleep(Duration::from_millis(200)).await,
+                }
+            }
+
+            format_client.shutdown();
+        });
+    };
+
+    //store the project as a snippet, and show the link to it
+    let share_code = move || {
+        spawn(async move {
+            let snippet = Snippet {
+                project: project.read().clone(),
+                toolchain: *toolchain.read(),
+                build: *build.read(),
+                author: current_login(),
+            };
+
+            let Ok(mut share_client) = PlaygroundClient::spawn(SERVER_ADDRESS).await else {
+                js_append_out(ServerMessage::error("Could not reach the server to share the code"));
+                return;
+            };
+            share_client.send_share_req(snippet);
+
+            loop {
+                match share_client.read() {
+                    Ok(Some(ServerMessage::Shared { id })) => {
+                        shared.set(Some(id));
+                        break;
+                    },
+                    Ok(Some(info @ ServerMessage::Error { .. })) => {
+                        if let Err(err) = js_append_out(info).await {
+                            eprintln!("{err}");
+                        }
+                        break;
+                    },
+                    Ok(Some(ServerMessage::Exit { .. })) | Err(_) => break,
+                    Ok(_) => task::sleep(Duration::from_millis(200)).await,
+                }
+            }
+
+            share_client.shutdown();
+        });
+    };
+
+    let mut apply_fix = move |diagnostic: Diagnostic| {
+        let Some(file_name) = diagnostic.machine_applicable_fixes().first().map(|span| span.file_name.clone()) else {
+            return;
+        };
+
+        let mut project = project.write();
+        let fixed = project.files
+            .iter_mut()
+            .find(|file| file.path == file_name)
+            .and_then(|file| Some((diagnostic.apply_fixes(&file_name, &file.contents)?, file)));
+
+        match fixed {
+            Some((fixed, file)) => {
+                file.contents = fixed;
+                fixes_stale.set(true);
+            },
+            None => {
+                js_append_out(ServerMessage::error("Could not apply the fix: the code changed since the build"));
+            },
+        }
+    };
+
+    //open the file of a diagnostic and select its span
+    let show_span = move |file_name: String, lines: (usize, usize), columns: (usize, usize)| {
+        spawn(async move {
+            if project.read().file(&file_name).is_none() {
+                return;
+            }
+            selected.set(file_name);
+            task::sleep(Duration::from_millis(50)).await;//let the editor show the file first
+            js_select_in_editor(lines.0, columns.0, lines.1, columns.1);
+        });
+    };
+
+    let add_file = move || {
+        spawn(async move {
+            let Some(path) = js_get_in("playground_new_file").await.ok().and_then(|v| v.as_str().map(str::to_string)) else {
+                return;
+            };
+
+            if let Err(err) = validate_path(&path) {
+                js_append_out(ServerMessage::error(err.to_string()));
+            } else if project.read().file(&path).is_some() {
+                js_append_out(ServerMessage::error(format!("'{path}' already exists")));
+            } else {
+                project.write().files.push(ProjectFile::new(path.clone(), ""));
+                selected.set(path);
+                js_clear_in("playground_new_file");
+            }
+        });
+    };
+
     let send_input = move || {
         spawn(async move {
             match js_get_in("playground_stdin").await {//get 'playground_stdin' value
@@ -202,8 +570,7 @@ pub fn Playground() -> Element {
                             client.write().send_input_req(input.to_string());//send input to stream
                         },
                         None => {
-                            js_append_out(JsonInfo::from_string(
-                                "error".to_string(),
+                            js_append_out(ServerMessage::error(
                                 format!(
                                     "{}{}",
                                     "Could not send input: ",
@@ -214,8 +581,7 @@ pub fn Playground() -> Element {
                     }
                 },
                 Err(err) => {
-                    js_append_out(JsonInfo::from_string(
-                        "error".to_string(),
+                    js_append_out(ServerMessage::error(
                         format!("Could not send input: {err}")
                     ));
                 },
@@ -232,11 +598,61 @@ pub fn Playground() -> Element {
             h1 { "Rust Playground" }
 
             div { id: "playground_editor",
-                textarea {
-                    id: "playground_file_input",
-                    name: "playground_file_input",
-                    class: "editor-textarea",
-                    "{DEFAULT_FN}"
+                div { class: "editor-row",
+                    div { id: "playground_file_tree", class: "file-tree",
+                        for path in project.read().files.iter().map(|file| file.path.clone()) {
+                            div {
+                                class: if *selected.read() == path { "file-entry selected" } else { "file-entry" },
+                                onclick: {
+                                    let path = path.clone();
+                                    move |_| selected.set(path.clone())
+                                },
+                                span { "{path}" }
+                                if project.read().files.len() > 1 {
+                                    button {
+                                        class: "file-remove-btn",
+                                        onclick: {
+                                            let path = path.clone();
+                                            move |evt: Event<MouseData>| {
+                                                evt.stop_propagation();
+                                                project.write().files.retain(|file| file.path != path);
+                                                if *selected.read() == path {
+                                                    let first = project.read().files[0].path.clone();
+                                                    selected.set(first);
+                                                }
+                                            }
+                                        },
+                                        "✕"
+                                    }
+                                }
+                            }
+                        }
+                        div { class: "file-add",
+                            input {
+                                id: "playground_new_file",
+                                r#type: "text",
+                                placeholder: "src/utils.rs",
+                                class: "file-add-input"
+                            }
+                            button {
+                                class: "file-add-btn",
+                                onclick: move |_| add_file(),
+                                "+"
+                            }
+                        }
+                    }
+                    textarea {
+                        id: "playground_file_input",
+                        name: "playground_file_input",
+                        class: "editor-textarea",
+                        value: project.read().file(&selected.read()).map(|file| file.contents.clone()).unwrap_or_default(),
+                        oninput: move |evt| {
+                            let path = selected.read().clone();
+                            if let Some(file) = project.write().files.iter_mut().find(|file| file.path == path) {
+                                file.contents = evt.value();
+                            }
+                        },
+                    }
                 }
                 button {
                     id: "playground_file_input_btn",
@@ -247,7 +663,7 @@ pub fn Playground() -> Element {
                         } else {
                             shutdown_client.set(false);
                             run_compile_clicked.set(true);
-                            run_compile();
+                            run_compile(Request::RunCompile);
                         }
                     },
                     match *run_compile_clicked.read() {
@@ -255,7 +671,294 @@ pub fn Playground() -> Element {
                         false => "▶ Compile & Run",
                     }
                 }
-                
+                button {
+                    id: "playground_test_btn",
+                    class: "format-btn",
+                    onclick: move |_| {
+                        if !*run_compile_clicked.read() {
+                            shutdown_client.set(false);
+                            run_compile_clicked.set(true);
+                            run_compile(Request::Test);
+                        }
+                    },
+                    "✔ Test"
+                }
+                button {
+                    id: "playground_lint_btn",
+                    class: "format-btn",
+                    onclick: move |_| {
+                        if !*run_compile_clicked.read() {
+                            shutdown_client.set(false);
+                            run_compile_clicked.set(true);
+                            run_compile(Request::Lint);
+                        }
+                    },
+                    "⚑ Lint"
+                }
+                select {
+                    id: "playground_inspect_target",
+                    class: "inspect-select",
+                    onchange: move |evt: Event<FormData>| {
+                        if let Some(target) = InspectTarget::ALL.into_iter().find(|target| target.as_str() == evt.value()) {
+                            inspect_target.set(target);
+                        }
+                    },
+                    for target in InspectTarget::ALL {
+                        option {
+                            value: target.as_str(),
+                            selected: *inspect_target.read() == target,
+                            "{target}"
+                        }
+                    }
+                }
+                button {
+                    id: "playground_inspect_btn",
+                    class: "format-btn",
+                    onclick: move |_| {
+                        if !*run_compile_clicked.read() {
+                            shutdown_client.set(false);
+                            run_compile_clicked.set(true);
+                            run_compile(Request::Inspect(*inspect_target.read()));
+                        }
+                    },
+                    "🔍 Inspect"
+                }
+                button {
+                    id: "playground_format_btn",
+                    class: "format-btn",
+                    onclick: move |_| format_code(),
+                    "✎ Format"
+                }
+                button {
+                    id: "playground_share_btn",
+                    class: "format-btn",
+                    onclick: move |_| share_code(),
+                    "🔗 Share"
+                }
+                if let Some(id) = shared.read().clone() {
+                    span { id: "playground_share_link", class: "share-link",
+                        Link { to: Route::SharedPlayground { id: id.clone() }, "/playground/{id}" }
+                    }
+                }
+
+                    div { id: "playground_toolchain", class: "toolchain-picker",
+                        label {
+                            "Channel: "
+                            select {
+                                onchange: move |evt: Event<FormData>| {
+                                    if let Some(channel) = Channel::ALL.into_iter().find(|channel| channel.as_str() == evt.value()) {
+                                        toolchain.write().channel = channel;
+                                    }
+                                },
+                                for channel in Channel::ALL {
+                                    option {
+                                        value: channel.as_str(),
+                                        selected: toolchain.read().channel == channel,
+                                        "{channel}"
+                                    }
+                                }
+                            }
+                        }
+                        label {
+                            "Edition: "
+                            select {
+                                onchange: move |evt: Event<FormData>| {
+                                    if let Some(edition) = Edition::ALL.into_iter().find(|edition| edition.as_str() == evt.value()) {
+                                        toolchain.write().edition = edition;
+                                    }
+                                },
+                                for edition in Edition::ALL {
+                                    option {
+                                        value: edition.as_str(),
+                                        selected: toolchain.read().edition == edition,
+                                        "{edition}"
+                                    }
+                                }
+                            }
+                        }
+                        label {
+                            "Profile: "
+                            select {
+                                onchange: move |evt: Event<FormData>| {
+                                    if let Some(profile) = BuildProfile::ALL.into_iter().find(|profile| profile.as_str() == evt.value()) {
+                                        build.write().profile = profile;
+                                    }
+                                },
+                                for profile in BuildProfile::ALL {
+                                    option {
+                                        value: profile.as_str(),
+                                        selected: build.read().profile == profile,
+                                        "{profile}"
+                                    }
+                                }
+                            }
+                        }
+                        label {
+                            "Opt-level: "
+                            select {
+                                //empty value: the one of the profile
+                                onchange: move |evt: Event<FormData>| {
+                                    build.write().opt_level = OptLevel::ALL.into_iter().find(|level| level.as_str() == evt.value());
+                                },
+                                option { value: "", selected: build.read().opt_level.is_none(), "default" }
+                                for level in OptLevel::ALL {
+                                    option {
+                                        value: level.as_str(),
+                                        selected: build.read().opt_level == Some(level),
+                                        "{level}"
+                                    }
+                                }
+                            }
+                        }
+                        label {
+                            "Overflow checks: "
+                            select {
+                                onchange: move |evt: Event<FormData>| {
+                                    build.write().overflow_checks = match evt.value().as_str() {
+                                        "on" => Some(true),
+                                        "off" => Some(false),
+                                        _ => None,
+                                    };
+                                },
+                                option { value: "", selected: build.read().overflow_checks.is_none(), "default" }
+                                option { value: "on", selected: build.read().overflow_checks == Some(true), "on" }
+                                option { value: "off", selected: build.read().overflow_checks == Some(false), "off" }
+                            }
+                        }
+                    }
+
+                    div { id: "playground_crates", class: "crate-picker",
+                        span { class: "crate-picker-title", "Crates:" }
+                        for krate in available_crates.read().iter().cloned() {
+                            label {
+                                class: "crate-entry",
+                                title: "{krate.description}",
+                                input {
+                                    r#type: "checkbox",
+                                    checked: project.read().dependencies.contains(&krate.name),
+                                    onchange: {
+                                        let name = krate.name.clone();
+                                        move |evt: Event<FormData>| {
+                                            let dependencies = &mut project.write().dependencies;
+                                            dependencies.retain(|dependency| *dependency != name);
+                                            if evt.checked() {
+                                                dependencies.push(name.clone());
+                                            }
+                                        }
+                                    },
+                                }
+                                "{krate.name} {krate.version}"
+                            }
+                        }
+                    }
+
+                    div { id: "playground_diagnostics", class: "diagnostics-panel",
+                        for diagnostic in diagnostics.read().iter().filter(|d| d.primary_span().is_some()).cloned() {
+                            div {
+                                class: format!("diagnostic diagnostic-{}", diagnostic.level).replace(' ', "-"),
+                                onclick: {
+                                    let span = diagnostic.primary_span().cloned();
+                                    move |_| {
+                                        if let Some(span) = &span {
+                                            show_span(
+                                                span.file_name.clone(),
+                                                (span.line_start, span.line_end),
+                                                (span.column_start, span.column_end),
+                                            );
+                                        }
+                                    }
+                                },
+                                span { "{diagnostic}" }
+                                if !*fixes_stale.read() && !diagnostic.machine_applicable_fixes().is_empty() {
+                                    button {
+                                        class: "fix-btn",
+                                        onclick: {
+                                            let diagnostic = diagnostic.clone();
+                                            move |evt: Event<MouseData>| {
+                                                evt.stop_propagation();
+                                                apply_fix(diagnostic.clone());
+                                            }
+                                        },
+                                        "Apply fix"
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if let Some((target, html)) = inspection.read().clone() {
+                        div { id: "playground_inspect", class: "inspect-panel",
+                            span { class: "inspect-title", "{target}" }
+                            //read-only: syntect's <pre>, not an editor
+                            div { class: "inspect-code", dangerous_inner_html: "{html}" }
+                        }
+                    }
+
+                    if !tests.read().is_empty() {
+                        div { id: "playground_tests", class: "tests-panel",
+                            if let Some(summary) = *test_summary.read() {
+                                span { class: "tests-title", "Tests: {summary}" }
+                            }
+                            for result in tests.read().iter().cloned() {
+                                details { class: format!("test test-{:?}", result.status).to_lowercase(),
+                                    summary {
+                                        span { class: "test-status", "{result.status}" }
+                                        span { " {result.name}" }
+                                        span { class: "test-suite", " ({result.suite})" }
+                                        if let Some(micros) = result.duration_micros {
+                                            span { class: "test-duration", " {micros as f64 / 1000.0:.1} ms" }
+                                        }
+                                    }
+                                    if !result.output.is_empty() {
+                                        pre { class: "test-output", "{result.output}" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if !lints.read().is_empty() {
+                        div { id: "playground_lints", class: "lints-panel",
+                            span { class: "lints-title", "Lints ({lints.read().len()})" }
+                            for lint in lints.read().iter().cloned() {
+                                div {
+                                    class: format!("lint lint-{}", lint.level).replace(' ', "-"),
+                                    onclick: {
+                                        let span = lint.span.clone();
+                                        move |_| {
+                                            if let Some(span) = &span {
+                                                show_span(
+                                                    span.file_name.clone(),
+                                                    (span.line_start, span.line_end),
+                                                    (span.column_start, span.column_end),
+                                                );
+                                            }
+                                        }
+                                    },
+                                    div {
+                                        span { class: "lint-name", "{lint.name}" }
+                                        span { " {lint.message}" }
+                                        if let Some(span) = &lint.span {
+                                            span { class: "lint-location", " ({span.file_name}:{span.line_start}:{span.column_start})" }
+                                        }
+                                    }
+                                    for help in lint.help.iter() {
+                                        div { class: "lint-help", "help: {help}" }
+                                    }
+                                    if let Some(url) = &lint.docs_url {
+                                        a {
+                                            class: "lint-docs",
+                                            href: "{url}",
+                                            target: "_blank",
+                                            onclick: move |evt: Event<MouseData>| evt.stop_propagation(),
+                                            "docs"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
                     code {
                         id: "playground_file_output",
                         
@@ -276,15 +979,26 @@ pub fn Playground() -> Element {
                     button {
                         id: "playground_command_btn",
                         onclick: move |_| {
+                            if *run_compile_clicked.read() {
+                                return;//one request at a time
+                            }
+
                             spawn(async move {
                                 match js_get_in("playground_command").await {
                                     Ok(cmd) => {
-                                        if let Some(command) = cmd.as_str() {
-                                            client.write().send_input_req(format!("__CMD__{}", command));
+                                        match CargoCommand::parse(cmd.as_str().unwrap_or_default()) {
+                                            Ok(command) => {
+                                                shutdown_client.set(false);
+                                                run_compile_clicked.set(true);
+                                                run_compile(Request::Command(command));
+                                            },
+                                            Err(err) => {
+                                                js_append_out(ServerMessage::error(format!("Command error: {err}")));
+                                            },
                                         }
                                     },
                                     Err(err) => {
-                                        js_append_out(JsonInfo::from_string("error".to_string(), format!("Command error: {err}")));
+                                        js_append_out(ServerMessage::error(format!("Command error: {err}")));
                                     }
                                 }
                             });
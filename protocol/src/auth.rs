@@ -0,0 +1,93 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+
+/// Shortest key the tokens can be signed with.
+pub const MIN_KEY_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a session token says about the client: issued by the account backend
+/// of the app, sent with [`crate::ClientMessage::Hello`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionClaims {
+    /// Login of the account.
+    pub login: String,
+    /// Picks the limits the account is served with (e.g. `teacher`); the
+    /// server's default ones if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Unix time (in seconds) the token stops being accepted at.
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    #[error("the token is malformed")]
+    Malformed,
+    #[error("the token signature is not valid")]
+    BadSignature,
+    #[error("the token expired")]
+    Expired,
+}
+
+/// Signs `claims` with `key` (HMAC-SHA256): the token is
+/// `<claims as base64 JSON>.<signature as base64>`.
+pub fn sign_token(claims: &SessionClaims, key: &[u8]) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).expect("claims are always serializable"));
+    let signature = URL_SAFE_NO_PAD.encode(mac(key, &payload).finalize().into_bytes());
+
+    format!("{payload}.{signature}")
+}
+
+/// Checks the signature of `token` with `key` and that it's still valid at
+/// `now` (unix time, in seconds).
+pub fn verify_token(token: &str, key: &[u8], now: u64) -> Result<SessionClaims, TokenError> {
+    let (payload, signature) = token.split_once('.').ok_or(TokenError::Malformed)?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| TokenError::Malformed)?;
+
+    //constant time comparison
+    mac(key, payload).verify_slice(&signature).map_err(|_| TokenError::BadSignature)?;
+
+    let claims: SessionClaims = URL_SAFE_NO_PAD.decode(payload).ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(TokenError::Malformed)?;
+
+    if claims.expires_at <= now {
+        return Err(TokenError::Expired);
+    }
+    Ok(claims)
+}
+
+fn mac(key: &[u8], payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_signed_and_checked() {
+        let key = [7u8; MIN_KEY_LEN];
+        let claims = SessionClaims { login: "mario.rossi".to_string(), role: None, expires_at: 1_000 };
+        let token = sign_token(&claims, &key);
+
+        assert_eq!(verify_token(&token, &key, 999), Ok(claims.clone()));
+        assert_eq!(verify_token(&token, &key, 1_000), Err(TokenError::Expired));
+        assert_eq!(verify_token(&token, &[8u8; MIN_KEY_LEN], 999), Err(TokenError::BadSignature));
+
+        //another login with the old signature
+        let forged = SessionClaims { login: "admin".to_string(), ..claims };
+        let forged = sign_token(&forged, &[0u8; MIN_KEY_LEN]);
+        let (payload, _) = forged.split_once('.').unwrap();
+        let (_, signature) = token.split_once('.').unwrap();
+        assert_eq!(verify_token(&format!("{payload}.{signature}"), &key, 999), Err(TokenError::BadSignature));
+
+        assert_eq!(verify_token("no signature", &key, 999), Err(TokenError::Malformed));
+        assert_eq!(verify_token("e30.!!!", &key, 999), Err(TokenError::Malformed));
+    }
+}
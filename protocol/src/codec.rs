@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{ClientMessage, FRAME_HEADER_LEN, ProtocolError, ServerMessage, encode_message, framing::frame_len};
+
+/// [`tokio_util::codec`] implementation of the playground framing.
+///
+/// Decodes `D` and encodes `E`. A malformed payload is yielded as an inner
+/// `Err` so the connection survives it; only framing/io errors end the stream.
+pub struct MessageCodec<D, E> {
+    _marker: PhantomData<fn(E) -> D>,
+}
+
+/// Codec used by the server: reads [`ClientMessage`]s, writes [`ServerMessage`]s.
+pub type ServerCodec = MessageCodec<ClientMessage, ServerMessage>;
+/// Codec used by async clients: reads [`ServerMessage`]s, writes [`ClientMessage`]s.
+pub type ClientCodec = MessageCodec<ServerMessage, ClientMessage>;
+
+impl<D, E> MessageCodec<D, E> {
+    pub fn new() -> Self {
+        MessageCodec { _marker: PhantomData }
+    }
+}
+
+impl<D, E> Default for MessageCodec<D, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: DeserializeOwned, E> Decoder for MessageCodec<D, E> {
+    type Item = Result<D, ProtocolError>;
+    type Error = ProtocolError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < FRAME_HEADER_LEN {
+            return Ok(None);
+        }
+
+        let len = frame_len(&src[..FRAME_HEADER_LEN])?;
+        if src.len() < FRAME_HEADER_LEN + len {
+            src.reserve(FRAME_HEADER_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(FRAME_HEADER_LEN);
+        let payload = src.split_to(len);
+        Ok(Some(serde_json::from_slice(&payload).map_err(ProtocolError::from)))
+    }
+}
+
+impl<D, E: Serialize> Encoder<E> for MessageCodec<D, E> {
+    type Error = ProtocolError;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&encode_message(&item)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_message, write_message};
+
+    #[test]
+    fn codec_matches_blocking_framing() {
+        let mut wire = Vec::new();
+        write_message(&mut wire, &ClientMessage::Input { data: "1\n".to_string() }).unwrap();
+        wire.extend_from_slice(&(7u32).to_be_bytes());
+        wire.extend_from_slice(b"garbage");
+        write_message(&mut wire, &ClientMessage::Exit).unwrap();
+
+        let mut codec = ServerCodec::new();
+        let mut src = BytesMut::from(&wire[..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap().unwrap().unwrap(),
+            ClientMessage::Input { data: "1\n".to_string() }
+        );
+        assert!(codec.decode(&mut src).unwrap().unwrap().is_err());
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap().unwrap(), ClientMessage::Exit);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+
+        let mut dst = BytesMut::new();
+        codec.encode(ServerMessage::status("EXECUTION ----------"), &mut dst).unwrap();
+        let mut encoded = dst.to_vec();
+        assert_eq!(
+            decode_message::<ServerMessage>(&mut encoded).unwrap(),
+            Some(ServerMessage::status("EXECUTION ----------"))
+        );
+    }
+}
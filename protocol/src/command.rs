@@ -0,0 +1,261 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// Cargo subcommands the playground can run on a project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CargoSubcommand {
+    Check,
+    Build,
+    Run,
+    Test,
+    Clippy,
+    Fmt,
+    Doc,
+    Tree,
+}
+
+/// A whitelisted cargo invocation, e.g. `cargo test --lib -- --nocapture`.
+///
+/// Built with [`CargoCommand::parse`]: every argument is checked against the
+/// flags allowed for its subcommand, so it can be passed to cargo as it is
+/// (never through a shell).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CargoCommand {
+    pub subcommand: CargoSubcommand,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CommandError {
+    #[error("empty command")]
+    Empty,
+    #[error("only cargo commands can be run, not '{0}'")]
+    NotCargo(String),
+    #[error("'cargo {0}' is not available (try check, build, run, test, clippy, fmt, doc or tree)")]
+    UnknownSubcommand(String),
+    #[error("'{arg}' is not allowed with 'cargo {subcommand}'")]
+    ForbiddenArgument { subcommand: CargoSubcommand, arg: String },
+}
+
+//flags allowed with every subcommand but fmt
+const COMMON_FLAGS: [&str; 4] = ["-q", "--quiet", "-v", "--verbose"];
+const TARGET_FLAGS: [&str; 6] = ["--release", "--all-targets", "--lib", "--bins", "--examples", "--tests"];
+//flags followed by the name of a target
+const TARGET_VALUE_FLAGS: [&str; 3] = ["--bin", "--example", "--test"];
+//flags of the test harness, after '--'
+const HARNESS_FLAGS: [&str; 5] = ["--nocapture", "--ignored", "--include-ignored", "--exact", "--show-output"];
+
+impl CargoSubcommand {
+    pub const ALL: [CargoSubcommand; 8] = [
+        CargoSubcommand::Check,
+        CargoSubcommand::Build,
+        CargoSubcommand::Run,
+        CargoSubcommand::Test,
+        CargoSubcommand::Clippy,
+        CargoSubcommand::Fmt,
+        CargoSubcommand::Doc,
+        CargoSubcommand::Tree,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CargoSubcommand::Check => "check",
+            CargoSubcommand::Build => "build",
+            CargoSubcommand::Run => "run",
+            CargoSubcommand::Test => "test",
+            CargoSubcommand::Clippy => "clippy",
+            CargoSubcommand::Fmt => "fmt",
+            CargoSubcommand::Doc => "doc",
+            CargoSubcommand::Tree => "tree",
+        }
+    }
+
+    /// Flags without a value this subcommand accepts.
+    fn allows_flag(&self, arg: &str) -> bool {
+        match self {
+            CargoSubcommand::Fmt => matches!(arg, "--check" | "-v" | "--verbose" | "-q" | "--quiet"),
+            CargoSubcommand::Tree => COMMON_FLAGS.contains(&arg) || matches!(arg, "-d" | "--duplicates"),
+            CargoSubcommand::Doc => {
+                COMMON_FLAGS.contains(&arg)
+                    || TARGET_FLAGS.contains(&arg)
+                    || matches!(arg, "--no-deps" | "--document-private-items")
+            },
+            //built and run like a run_compile request, which only picks the profile
+            CargoSubcommand::Run => arg == "--release",
+            _ => COMMON_FLAGS.contains(&arg) || TARGET_FLAGS.contains(&arg),
+        }
+    }
+
+    /// Flags followed by a value this subcommand accepts.
+    fn allows_value_flag(&self, arg: &str) -> bool {
+        match self {
+            CargoSubcommand::Fmt | CargoSubcommand::Run => false,
+            CargoSubcommand::Tree => arg == "--depth",
+            _ => TARGET_VALUE_FLAGS.contains(&arg),
+        }
+    }
+}
+
+impl CargoCommand {
+    pub fn new(subcommand: CargoSubcommand) -> Self {
+        CargoCommand { subcommand, args: Vec::new() }
+    }
+
+    /// Parses what a user typed, e.g. `cargo check --all-targets`; the
+    /// leading `cargo` is optional.
+    pub fn parse(line: &str) -> Result<Self, CommandError> {
+        let mut words = line.split_whitespace().peekable();
+        if words.peek() == Some(&"cargo") {
+            words.next();
+        }
+
+        let Some(name) = words.next() else {
+            return Err(CommandError::Empty);
+        };
+        let subcommand = CargoSubcommand::ALL
+            .into_iter()
+            .find(|subcommand| subcommand.as_str() == name)
+            .ok_or_else(|| match line.split_whitespace().next() {
+                Some("cargo") => CommandError::UnknownSubcommand(name.to_string()),
+                _ => CommandError::NotCargo(name.to_string()),
+            })?;
+
+        let command = CargoCommand { subcommand, args: words.map(str::to_string).collect() };
+        command.validate()?;
+        Ok(command)
+    }
+
+    /// Checks every argument against the whitelist of the subcommand.
+    pub fn validate(&self) -> Result<(), CommandError> {
+        let forbidden = |arg: &str| Err(CommandError::ForbiddenArgument {
+            subcommand: self.subcommand,
+            arg: arg.to_string(),
+        });
+
+        let mut args = self.args.iter();
+        let mut harness = false;//after '--', only for tests
+        while let Some(arg) = args.next() {
+            let arg = arg.as_str();
+
+            if harness {
+                if !HARNESS_FLAGS.contains(&arg) && !is_test_filter(arg) {
+                    return forbidden(arg);
+                }
+            } else if arg == "--" && self.subcommand == CargoSubcommand::Test {
+                harness = true;
+            } else if self.subcommand.allows_value_flag(arg) {
+                match args.next() {
+                    Some(value) if is_test_filter(value) => {},
+                    Some(value) => return forbidden(value),
+                    None => return forbidden(arg),
+                }
+            } else if self.subcommand == CargoSubcommand::Test && is_test_filter(arg) {
+                //a filter on the names of the tests
+            } else if !self.subcommand.allows_flag(arg) {
+                return forbidden(arg);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The arguments of a `cargo test`, split between cargo (the targets to
+    /// build, e.g. `--lib`) and the test harness (the filters and the flags
+    /// after `--`).
+    pub fn test_args(&self) -> (Vec<String>, Vec<String>) {
+        let mut cargo = Vec::new();
+        let mut harness = Vec::new();
+
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                harness.extend(args.by_ref().cloned());
+            } else if self.subcommand.allows_value_flag(arg) {
+                cargo.push(arg.clone());
+                cargo.extend(args.next().cloned());
+            } else if arg.starts_with('-') {
+                cargo.push(arg.clone());
+            } else {
+                harness.push(arg.clone());
+            }
+        }
+
+        (cargo, harness)
+    }
+}
+
+/// Names of targets and tests, e.g. `integration` or `tests::adds_two`.
+fn is_test_filter(arg: &str) -> bool {
+    !arg.is_empty()
+        && !arg.starts_with('-')
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == ':')
+}
+
+impl fmt::Display for CargoSubcommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for CargoCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cargo {}", self.subcommand)?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_whitelisted_commands() {
+        let command = CargoCommand::parse("cargo test --lib adds -- --nocapture").unwrap();
+        assert_eq!(command.subcommand, CargoSubcommand::Test);
+        assert_eq!(command.args, ["--lib", "adds", "--", "--nocapture"]);
+        assert_eq!(command.to_string(), "cargo test --lib adds -- --nocapture");
+
+        assert_eq!(CargoCommand::parse("check").unwrap(), CargoCommand::new(CargoSubcommand::Check));
+        assert!(CargoCommand::parse("cargo fmt --check").is_ok());
+        assert!(CargoCommand::parse("cargo tree --depth 1").is_ok());
+        assert!(CargoCommand::parse("cargo build --bin playground --release").is_ok());
+        assert!(CargoCommand::parse("cargo run --release").is_ok());
+    }
+
+    #[test]
+    fn splits_test_arguments() {
+        let command = CargoCommand::parse("cargo test --test integration adds --release -- --exact --nocapture").unwrap();
+        let (cargo, harness) = command.test_args();
+        assert_eq!(cargo, ["--test", "integration", "--release"]);
+        assert_eq!(harness, ["adds", "--exact", "--nocapture"]);
+    }
+
+    #[test]
+    fn rejects_everything_else() {
+        assert_eq!(CargoCommand::parse("  "), Err(CommandError::Empty));
+        assert_eq!(CargoCommand::parse("rm -rf /"), Err(CommandError::NotCargo("rm".to_string())));
+        assert_eq!(CargoCommand::parse("cargo install ripgrep"), Err(CommandError::UnknownSubcommand("install".to_string())));
+        assert!(matches!(CargoCommand::parse("cargo check;"), Err(CommandError::UnknownSubcommand(_))));
+
+        for line in [
+            "cargo check ; rm -rf /",
+            "cargo build --target-dir /tmp",
+            "cargo check --manifest-path /etc/Cargo.toml",
+            "cargo run -- $(whoami)",
+            "cargo run --quiet",
+            "cargo run --bin playground",
+            "cargo test -- --logfile /tmp/x",
+            "cargo fmt --release",
+            "cargo build --bin ../x",
+            "cargo clippy -- -D warnings",
+            "cargo check --config build.rustc-wrapper='sh'",
+        ] {
+            assert!(matches!(CargoCommand::parse(line), Err(CommandError::ForbiddenArgument { .. })), "{line}");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// A compiler message (error, warning, ...), as reported by rustc.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    /// Error code, e.g. `E0308`.
+    pub code: Option<String>,
+    pub message: String,
+    pub spans: Vec<DiagnosticSpan>,
+    /// Notes and help attached to this diagnostic (they carry most suggestions).
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    /// The diagnostic as rustc would print it.
+    pub rendered: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+    Help,
+    FailureNote,
+    /// Internal compiler error.
+    Ice,
+}
+
+/// A region of the source a diagnostic refers to.
+///
+/// Lines and columns start from 1; byte offsets start from 0 and are relative
+/// to the beginning of the file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    /// `false` for the secondary spans that only add context.
+    pub is_primary: bool,
+    pub label: Option<String>,
+    /// Text that should replace the span to fix the problem.
+    pub suggested_replacement: Option<String>,
+    pub suggestion_applicability: Option<Applicability>,
+}
+
+/// How confident the compiler is about a suggestion.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Applicability {
+    /// Can be applied as it is.
+    MachineApplicable,
+    MaybeIncorrect,
+    HasPlaceholders,
+    Unspecified,
+}
+
+impl Diagnostic {
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+
+    /// Every replacement of this diagnostic and its children that can be
+    /// applied without review, sorted from the end of the file so they can be
+    /// applied one after the other without shifting the offsets.
+    pub fn machine_applicable_fixes(&self) -> Vec<&DiagnosticSpan> {
+        let mut fixes: Vec<&DiagnosticSpan> = std::iter::once(self)
+            .chain(self.children.iter())
+            .flat_map(|diagnostic| diagnostic.spans.iter())
+            .filter(|span| {
+                span.suggested_replacement.is_some()
+                    && span.suggestion_applicability == Some(Applicability::MachineApplicable)
+            })
+            .collect();
+
+        fixes.sort_by_key(|span| std::cmp::Reverse(span.byte_start));
+        fixes
+    }
+
+    /// Applies the [`Diagnostic::machine_applicable_fixes`] of `file_name` to
+    /// `source`, its contents.
+    ///
+    /// Returns `None` if there is nothing to apply or the spans don't fit
+    /// `source` (e.g. it was edited after the build).
+    pub fn apply_fixes(&self, file_name: &str, source: &str) -> Option<String> {
+        let fixes: Vec<&DiagnosticSpan> = self.machine_applicable_fixes()
+            .into_iter()
+            .filter(|span| span.file_name == file_name)
+            .collect();
+        if fixes.is_empty() {
+            return None;
+        }
+
+        let mut fixed = source.to_string();
+        for span in fixes {
+            let range = span.byte_start..span.byte_end;
+            if range.end > fixed.len() || !fixed.is_char_boundary(range.start) || !fixed.is_char_boundary(range.end) {
+                return None;
+            }
+            fixed.replace_range(range, span.suggested_replacement.as_deref().unwrap_or_default());
+        }
+
+        Some(fixed)
+    }
+}
+
+impl fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticLevel::Error => write!(f, "error"),
+            DiagnosticLevel::Warning => write!(f, "warning"),
+            DiagnosticLevel::Note => write!(f, "note"),
+            DiagnosticLevel::Help => write!(f, "help"),
+            DiagnosticLevel::FailureNote => write!(f, "failure-note"),
+            DiagnosticLevel::Ice => write!(f, "internal compiler error"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.level)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{code}]")?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(span) = self.primary_span() {
+            write!(f, " ({}:{}:{})", span.file_name, span.line_start, span.column_start)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(byte_start: usize, replacement: Option<&str>, applicability: Applicability) -> DiagnosticSpan {
+        DiagnosticSpan {
+            file_name: "src/main.rs".to_string(),
+            byte_start,
+            byte_end: byte_start + 1,
+            line_start: 1,
+            line_end: 1,
+            column_start: byte_start + 1,
+            column_end: byte_start + 2,
+            is_primary: replacement.is_none(),
+            label: None,
+            suggested_replacement: replacement.map(str::to_string),
+            suggestion_applicability: Some(applicability),
+        }
+    }
+
+    #[test]
+    fn fixes_come_from_children_sorted_from_the_end() {
+        let help = |spans| Diagnostic {
+            level: DiagnosticLevel::Help,
+            code: None,
+            message: "try this".to_string(),
+            spans,
+            children: Vec::new(),
+            rendered: None,
+        };
+        let diagnostic = Diagnostic {
+            level: DiagnosticLevel::Warning,
+            code: None,
+            message: "unused variable".to_string(),
+            spans: vec![span(4, None, Applicability::Unspecified)],
+            children: vec![
+                help(vec![span(4, Some("_x"), Applicability::MachineApplicable)]),
+                help(vec![
+                    span(8, Some("y"), Applicability::MachineApplicable),
+                    span(9, Some("z"), Applicability::MaybeIncorrect),
+                ]),
+            ],
+            rendered: None,
+        };
+
+        let starts: Vec<usize> = diagnostic.machine_applicable_fixes().iter().map(|s| s.byte_start).collect();
+        assert_eq!(starts, [8, 4]);
+        assert_eq!(diagnostic.primary_span().unwrap().byte_start, 4);
+
+        assert_eq!(diagnostic.apply_fixes("src/main.rs", "let x = a;").as_deref(), Some("let _x = y;"));
+        assert_eq!(diagnostic.apply_fixes("src/main.rs", "let x"), None);
+        assert_eq!(diagnostic.apply_fixes("src/lib.rs", "let x = a;"), None);
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("frame of {len} bytes exceeds the limit of {max} bytes")]
+    FrameTooLarge { len: usize, max: usize },
+    #[error("malformed message: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+impl ProtocolError {
+    /// `true` when a non-blocking stream simply had nothing to read/write yet.
+    pub fn is_would_block(&self) -> bool {
+        matches!(self, ProtocolError::Io(e) if e.kind() == io::ErrorKind::WouldBlock)
+    }
+
+    /// `true` when the connection can't be used anymore (as opposed to a
+    /// single bad message).
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            ProtocolError::Io(e) => e.kind() != io::ErrorKind::WouldBlock,
+            ProtocolError::FrameTooLarge { .. } => true,
+            ProtocolError::Malformed(_) => false,
+        }
+    }
+}
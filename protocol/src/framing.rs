@@ -0,0 +1,302 @@
+use std::io::{self, Read, Write};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::ProtocolError;
+
+/// Biggest JSON payload accepted in a single frame.
+pub const MAX_FRAME_LEN: usize = 128 * 1024;
+/// Size of the big-endian `u32` length prefix.
+pub const FRAME_HEADER_LEN: usize = 4;
+
+/// Serializes `msg` into a complete frame: `{len: u32 BE}{json}`.
+pub fn encode_message<T: Serialize>(msg: &T) -> Result<Vec<u8>, ProtocolError> {
+    let payload = serde_json::to_vec(msg)?;
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge { len: payload.len(), max: MAX_FRAME_LEN });
+    }
+
+    //N.B.: data_len necessary to determine start/end of msgs
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Pops the first complete frame out of `buf`, if there is one.
+///
+/// Incomplete frames are left untouched so more bytes can be appended later.
+pub fn decode_message<T: DeserializeOwned>(buf: &mut Vec<u8>) -> Result<Option<T>, ProtocolError> {
+    if buf.len() < FRAME_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let len = frame_len(&buf[..FRAME_HEADER_LEN])?;
+    if buf.len() < FRAME_HEADER_LEN + len {
+        return Ok(None);
+    }
+
+    let frame: Vec<u8> = buf.drain(..FRAME_HEADER_LEN + len).skip(FRAME_HEADER_LEN).collect();
+    Ok(Some(serde_json::from_slice(&frame)?))
+}
+
+/// Blocking read of exactly one message.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, ProtocolError> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    reader.read_exact(&mut header)?;// read size first
+
+    let len = frame_len(&header)?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;//read n (= size) bytes
+
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+pub fn write_message<T: Serialize>(writer: &mut impl Write, msg: &T) -> Result<(), ProtocolError> {
+    writer.write_all(&encode_message(msg)?)?;
+    let _f = writer.flush();//no need to handle
+    Ok(())
+}
+
+pub(crate) fn frame_len(header: &[u8]) -> Result<usize, ProtocolError> {
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge { len, max: MAX_FRAME_LEN });
+    }
+    Ok(len)
+}
+
+//MessageReader -------------------------------------------------------------
+/// Buffers bytes coming from a (possibly non-blocking) reader so that a frame
+/// split across several reads is never lost.
+pub struct MessageReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> MessageReader<R> {
+    pub fn new(inner: R) -> Self {
+        MessageReader { inner, buf: Vec::new() }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the next message if a whole frame is available, `Ok(None)` if
+    /// the reader would block before that.
+    pub fn try_read<T: DeserializeOwned>(&mut self) -> Result<Option<T>, ProtocolError> {
+        loop {
+            if let Some(msg) = decode_message(&mut self.buf)? {
+                return Ok(Some(msg));
+            }
+
+            let mut chunk = [0u8; 4096];
+            match self.inner.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        BuildOptions, BuildProfile, CargoCommand, Channel, ClientMessage, CrateInfo, Diagnostic, DiagnosticLevel, Edition,
+        InspectTarget, LintItem, OptLevel, Project, ProjectFile, RunLimits, ServerMessage, Snippet, TerminationReason,
+        TestResult, TestStatus, TestSummary, Toolchain,
+    };
+
+    fn client_messages() -> Vec<ClientMessage> {
+        vec![
+            ClientMessage::hello(),
+            ClientMessage::hello_with_token("eyJsb2dpbiI6Im1hcmlvIn0.c2lnbmF0dXJl"),
+            ClientMessage::RunCompile {
+                project: Project {
+                    files: vec![
+                        ProjectFile::new("src/main.rs", "mod utils;\n\nfn main() {\n    utils::ciao();\n}"),
+                        ProjectFile::new("src/utils.rs", "pub fn ciao() {\n    println!(\"ciao\");\n}"),
+                    ],
+                    dependencies: vec!["rand".to_string()],
+                },
+                toolchain: Toolchain { channel: Channel::Nightly, edition: Edition::E2018 },
+                build: BuildOptions { profile: BuildProfile::Release, overflow_checks: Some(true), opt_level: Some(OptLevel::S) },
+                limits: RunLimits { wall_time_secs: Some(5), ..Default::default() },
+            },
+            ClientMessage::Command {
+                command: CargoCommand::parse("cargo test -- --nocapture").unwrap(),
+                project: Project::single("fn main() {}"),
+                toolchain: Toolchain::default(),
+                limits: RunLimits::default(),
+            },
+            ClientMessage::Test {
+                project: Project::single("#[test]\nfn works() {}"),
+                toolchain: Toolchain::default(),
+                limits: RunLimits::default(),
+            },
+            ClientMessage::Lint { project: Project::single("fn main() { return; }"), toolchain: Toolchain::default() },
+            ClientMessage::Inspect {
+                project: Project::single("fn main() {}"),
+                target: InspectTarget::LlvmIr,
+                toolchain: Toolchain::default(),
+                build: BuildOptions::default(),
+            },
+            ClientMessage::ListCrates,
+            ClientMessage::Share {
+                snippet: Snippet {
+                    project: Project::single("fn main() {}"),
+                    toolchain: Toolchain { channel: Channel::Beta, edition: Edition::E2021 },
+                    build: BuildOptions::default(),
+                    author: Some("ferris".to_string()),
+                },
+            },
+            ClientMessage::LoadSnippet { id: "aZ09bY18cX".to_string() },
+            ClientMessage::Format { source: "fn main(){}".to_string(), edition: Edition::E2021 },
+            ClientMessage::Input { data: "42\n".to_string() },
+            ClientMessage::Exit,
+        ]
+    }
+
+    fn server_messages() -> Vec<ServerMessage> {
+        vec![
+            ServerMessage::welcome(None),
+            ServerMessage::welcome(Some("mario.rossi".to_string())),
+            ServerMessage::Queued { position: 3, estimated_wait_secs: 45 },
+            ServerMessage::Admitted,
+            ServerMessage::ShuttingDown { grace_period_secs: 30 },
+            ServerMessage::RateLimited { message: "6 runs per minute".to_string(), retry_after_secs: 8 },
+            ServerMessage::CrateList {
+                crates: vec![CrateInfo {
+                    name: "rand".to_string(),
+                    version: "0.9.0".to_string(),
+                    description: "Random number generators".to_string(),
+                }],
+            },
+            ServerMessage::status("Building..."),
+            ServerMessage::Shared { id: "aZ09bY18cX".to_string() },
+            ServerMessage::Snippet { id: "aZ09bY18cX".to_string(), snippet: Snippet::default() },
+            ServerMessage::Formatted { source: "fn main() {}\n".to_string() },
+            ServerMessage::FormatError { message: "expected expression, found `;`".to_string(), line: Some(2), column: Some(9) },
+            ServerMessage::Diagnostic {
+                diagnostic: Diagnostic {
+                    level: DiagnosticLevel::Error,
+                    code: Some("E0425".to_string()),
+                    message: "cannot find value `x` in this scope".to_string(),
+                    spans: Vec::new(),
+                    children: Vec::new(),
+                    rendered: None,
+                },
+            },
+            ServerMessage::Lint {
+                lint: LintItem {
+                    name: "clippy::needless_return".to_string(),
+                    level: DiagnosticLevel::Warning,
+                    message: "unneeded `return` statement".to_string(),
+                    span: None,
+                    help: vec!["remove `return`".to_string()],
+                    docs_url: Some("https://rust-lang.github.io/rust-clippy/master/index.html#needless_return".to_string()),
+                },
+            },
+            ServerMessage::CompilationResult { output: "warning: unused variable".to_string() },
+            ServerMessage::Stdout { data: "ciao\n".to_string() },
+            ServerMessage::Stderr { data: "thread 'main' panicked".to_string() },
+            ServerMessage::InspectOutput { target: InspectTarget::Asm, data: "main:\n\tret\n".to_string() },
+            ServerMessage::InspectFinished { target: InspectTarget::Asm, truncated: false },
+            ServerMessage::TestResult {
+                result: TestResult {
+                    name: "tests::adds_two".to_string(),
+                    suite: "src/lib.rs".to_string(),
+                    status: TestStatus::Failed,
+                    duration_micros: Some(1200),
+                    output: "assertion `left == right` failed".to_string(),
+                },
+            },
+            ServerMessage::TestSummary { summary: TestSummary { passed: 3, failed: 1, ignored: 0 } },
+            ServerMessage::RunFinished { reason: TerminationReason::Exited { code: 101 } },
+            ServerMessage::RunFinished { reason: TerminationReason::OutputLimitExceeded },
+            ServerMessage::error("ERR_PLAYGROUND_CP_EXE"),
+            ServerMessage::RequestCorrupted { reason: "unknown variant".to_string() },
+            ServerMessage::exit("gracefully exit"),
+        ]
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        let mut wire = Vec::new();
+        for msg in client_messages() {
+            write_message(&mut wire, &msg).unwrap();
+        }
+
+        let mut reader = Cursor::new(wire);
+        for msg in client_messages() {
+            assert_eq!(read_message::<ClientMessage>(&mut reader).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let mut wire = Vec::new();
+        for msg in server_messages() {
+            wire.extend(encode_message(&msg).unwrap());
+        }
+
+        for msg in server_messages() {
+            assert_eq!(decode_message::<ServerMessage>(&mut wire).unwrap(), Some(msg));
+        }
+        assert!(wire.is_empty());
+    }
+
+    #[test]
+    fn partial_frames_are_kept() {
+        let frame = encode_message(&ClientMessage::Exit).unwrap();
+        let mut buf = frame[..frame.len() - 1].to_vec();
+
+        assert_eq!(decode_message::<ClientMessage>(&mut buf).unwrap(), None);
+        buf.push(*frame.last().unwrap());
+        assert_eq!(decode_message::<ClientMessage>(&mut buf).unwrap(), Some(ClientMessage::Exit));
+    }
+
+    #[test]
+    fn message_reader_reassembles_frames() {
+        let mut wire = Vec::new();
+        for msg in client_messages() {
+            wire.extend(encode_message(&msg).unwrap());
+        }
+
+        let mut reader = MessageReader::new(Cursor::new(wire));
+        for msg in client_messages() {
+            assert_eq!(reader.try_read::<ClientMessage>().unwrap(), Some(msg));
+        }
+        assert!(reader.try_read::<ClientMessage>().unwrap_err().is_fatal());
+    }
+
+    #[test]
+    fn unknown_type_is_rejected() {
+        let mut buf = Vec::new();
+        let payload = br#"{"type":"shutdown"}"#;
+        buf.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(payload);
+
+        let err = decode_message::<ClientMessage>(&mut buf).unwrap_err();
+        assert!(matches!(err, ProtocolError::Malformed(_)));
+        assert!(!err.is_fatal());
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let project = Project::single("a".repeat(MAX_FRAME_LEN));
+        let err = encode_message(&ClientMessage::RunCompile { project, toolchain: Toolchain::default(), build: BuildOptions::default(), limits: RunLimits::default() }).unwrap_err();
+        assert!(matches!(err, ProtocolError::FrameTooLarge { .. }));
+
+        let mut buf = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes().to_vec();
+        let err = decode_message::<ClientMessage>(&mut buf).unwrap_err();
+        assert!(err.is_fatal());
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// What the compiler makes of a project, as asked by [`crate::ClientMessage::Inspect`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InspectTarget {
+    /// Assembly of the target machine.
+    Asm,
+    LlvmIr,
+    Mir,
+    Hir,
+    /// The source with every macro expanded.
+    Expanded,
+}
+
+impl InspectTarget {
+    pub const ALL: [InspectTarget; 5] = [
+        InspectTarget::Asm,
+        InspectTarget::LlvmIr,
+        InspectTarget::Mir,
+        InspectTarget::Hir,
+        InspectTarget::Expanded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InspectTarget::Asm => "asm",
+            InspectTarget::LlvmIr => "llvm_ir",
+            InspectTarget::Mir => "mir",
+            InspectTarget::Hir => "hir",
+            InspectTarget::Expanded => "expanded",
+        }
+    }
+
+    /// Whether the output reads like Rust code (and can be highlighted as such).
+    pub fn is_rust(&self) -> bool {
+        matches!(self, InspectTarget::Mir | InspectTarget::Hir | InspectTarget::Expanded)
+    }
+}
+
+impl fmt::Display for InspectTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InspectTarget::Asm => write!(f, "assembly"),
+            InspectTarget::LlvmIr => write!(f, "LLVM IR"),
+            InspectTarget::Mir => write!(f, "MIR"),
+            InspectTarget::Hir => write!(f, "HIR"),
+            InspectTarget::Expanded => write!(f, "expanded macros"),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Wire protocol shared by the playground server and the Dioxus client.
+//!
+//! Every message is a JSON document prefixed by its length as a big-endian
+//! `u32` (see [`framing`]).
+
+pub mod auth;
+#[cfg(feature = "tokio")]
+pub mod codec;
+pub mod command;
+pub mod diagnostic;
+pub mod error;
+pub mod framing;
+pub mod inspect;
+pub mod lint;
+pub mod message;
+pub mod profile;
+pub mod project;
+pub mod run;
+pub mod snippet;
+pub mod testing;
+pub mod toolchain;
+
+pub use auth::*;
+#[cfg(feature = "tokio")]
+pub use codec::*;
+pub use command::*;
+pub use diagnostic::*;
+pub use error::ProtocolError;
+pub use framing::*;
+pub use inspect::*;
+pub use lint::*;
+pub use message::*;
+pub use profile::*;
+pub use project::*;
+pub use run::*;
+pub use snippet::*;
+pub use testing::*;
+pub use toolchain::*;
+
+/// Bumped when a peer of the previous version can't be understood anymore,
+/// e.g. a field renamed or removed.
+///
+/// New optional fields (`#[serde(default)]`) and new message types are not
+/// incompatible: older clients leave the fields out, and skip the messages
+/// they don't know (see [`ProtocolError::is_fatal`]).
+pub const PROTOCOL_VERSION: u32 = 3;
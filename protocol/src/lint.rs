@@ -0,0 +1,116 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::{Diagnostic, DiagnosticLevel, DiagnosticSpan};
+
+/// Clippy's lints are documented at `<CLIPPY_DOCS>#<lint>`.
+pub const CLIPPY_DOCS: &str = "https://rust-lang.github.io/rust-clippy/master/index.html";
+
+/// A lint reported by clippy (or by rustc, e.g. `unused_variables`) while
+/// linting a project.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LintItem {
+    /// e.g. `clippy::needless_return` or `unused_variables`.
+    pub name: String,
+    pub level: DiagnosticLevel,
+    pub message: String,
+    /// Where the lint fired, if it points to the code.
+    pub span: Option<DiagnosticSpan>,
+    /// How to address the lint, e.g. "remove `return`".
+    #[serde(default)]
+    pub help: Vec<String>,
+    pub docs_url: Option<String>,
+}
+
+impl LintItem {
+    /// The lint carried by `diagnostic`, or `None` if it's a compile error
+    /// (or any other diagnostic without a lint name).
+    pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        let name = diagnostic.code.as_deref().filter(|code| !is_error_code(code))?;
+
+        let mut docs_url = None;
+        let mut help = Vec::new();
+        for child in diagnostic.children.iter().filter(|child| child.level == DiagnosticLevel::Help) {
+            match child.message.strip_prefix("for further information visit ") {
+                Some(url) => docs_url = Some(url.to_string()),
+                None => help.push(child.message.clone()),
+            }
+        }
+        if docs_url.is_none() {
+            docs_url = name.strip_prefix("clippy::").map(|lint| format!("{CLIPPY_DOCS}#{lint}"));
+        }
+
+        Some(LintItem {
+            name: name.to_string(),
+            level: diagnostic.level,
+            message: diagnostic.message.clone(),
+            span: diagnostic.primary_span().cloned(),
+            help,
+            docs_url,
+        })
+    }
+
+    pub fn is_clippy(&self) -> bool {
+        self.name.starts_with("clippy::")
+    }
+}
+
+/// Codes of compile errors, e.g. `E0425`.
+fn is_error_code(code: &str) -> bool {
+    code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+impl fmt::Display for LintItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.level, self.name, self.message)?;
+        if let Some(span) = &self.span {
+            write!(f, " ({}:{}:{})", span.file_name, span.line_start, span.column_start)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(code: &str, children: Vec<Diagnostic>) -> Diagnostic {
+        Diagnostic {
+            level: DiagnosticLevel::Warning,
+            code: Some(code.to_string()),
+            message: "unneeded `return` statement".to_string(),
+            spans: Vec::new(),
+            children,
+            rendered: None,
+        }
+    }
+
+    fn child(level: DiagnosticLevel, message: &str) -> Diagnostic {
+        Diagnostic { level, code: None, message: message.to_string(), spans: Vec::new(), children: Vec::new(), rendered: None }
+    }
+
+    #[test]
+    fn lints_are_told_apart_from_errors() {
+        let lint = LintItem::from_diagnostic(&diagnostic("clippy::needless_return", vec![
+            child(DiagnosticLevel::Help, "for further information visit https://rust-lang.github.io/rust-clippy/rust-1.95.0/index.html#needless_return"),
+            child(DiagnosticLevel::Note, "`#[warn(clippy::needless_return)]` on by default"),
+            child(DiagnosticLevel::Help, "remove `return`"),
+        ])).unwrap();
+        assert!(lint.is_clippy());
+        assert_eq!(lint.help, ["remove `return`"]);
+        assert_eq!(lint.docs_url.as_deref(), Some("https://rust-lang.github.io/rust-clippy/rust-1.95.0/index.html#needless_return"));
+        assert_eq!(lint.to_string(), "warning[clippy::needless_return]: unneeded `return` statement");
+
+        let lint = LintItem::from_diagnostic(&diagnostic("clippy::needless_return", Vec::new())).unwrap();
+        assert_eq!(lint.docs_url, Some(format!("{CLIPPY_DOCS}#needless_return")));
+
+        let lint = LintItem::from_diagnostic(&diagnostic("unused_variables", Vec::new())).unwrap();
+        assert!(!lint.is_clippy());
+        assert_eq!(lint.docs_url, None);
+
+        assert_eq!(LintItem::from_diagnostic(&diagnostic("E0425", Vec::new())), None);
+        let mut summary = diagnostic("", Vec::new());
+        summary.code = None;
+        assert_eq!(LintItem::from_diagnostic(&summary), None);
+    }
+}
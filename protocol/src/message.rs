@@ -0,0 +1,251 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::{BuildOptions, CargoCommand, CrateInfo, Diagnostic, Edition, InspectTarget, LintItem, Project, RunLimits, Snippet, TerminationReason, TestResult, TestSummary, Toolchain};
+
+//ClientMessage -------------------------------------------------------------
+/// Requests sent by the playground client to the server.
+///
+/// Serialized as `{"type": "<variant>", ...fields}`: an unknown or misspelled
+/// `type` fails to deserialize instead of being silently ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// First message of every connection; `token` ties the session to an
+    /// account (see [`crate::sign_token`]).
+    Hello {
+        protocol_version: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// Build the given project and run its binary (`src/main.rs`), if any.
+    RunCompile {
+        project: Project,
+        #[serde(default)]
+        toolchain: Toolchain,
+        #[serde(default)]
+        build: BuildOptions,
+        #[serde(default)]
+        limits: RunLimits,
+    },
+    /// Run a cargo command on the given project; `cargo run` behaves like
+    /// [`ClientMessage::RunCompile`].
+    Command {
+        command: CargoCommand,
+        project: Project,
+        #[serde(default)]
+        toolchain: Toolchain,
+        #[serde(default)]
+        limits: RunLimits,
+    },
+    /// Build the tests of the given project and run them.
+    Test {
+        project: Project,
+        #[serde(default)]
+        toolchain: Toolchain,
+        #[serde(default)]
+        limits: RunLimits,
+    },
+    /// Run clippy on the given project, without running it.
+    Lint {
+        project: Project,
+        #[serde(default)]
+        toolchain: Toolchain,
+    },
+    /// Compile the given project and send back `target` instead of running it.
+    Inspect {
+        project: Project,
+        target: InspectTarget,
+        #[serde(default)]
+        toolchain: Toolchain,
+        #[serde(default)]
+        build: BuildOptions,
+    },
+    /// Asks which crates projects can depend on.
+    ListCrates,
+    /// Store `snippet` for good, to share it.
+    Share { snippet: Snippet },
+    /// Asks the snippet shared as `id`.
+    LoadSnippet { id: String },
+    /// Format a single source file with rustfmt.
+    Format {
+        source: String,
+        #[serde(default)]
+        edition: Edition,
+    },
+    /// Line forwarded to the stdin of the running program.
+    Input { data: String },
+    /// Stop whatever is running and close the session.
+    Exit,
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientMessage::Hello { protocol_version, token: None } => write!(f, "hello (v{protocol_version})"),
+            ClientMessage::Hello { protocol_version, token: Some(_) } => write!(f, "hello (v{protocol_version}, with token)"),
+            ClientMessage::RunCompile { project, toolchain, build, .. } => {
+                write!(f, "run_compile ({} files, {} bytes, {toolchain}, {build})", project.files.len(), project.len())
+            },
+            ClientMessage::Command { command, toolchain, .. } => write!(f, "command: {command} ({toolchain})"),
+            ClientMessage::Test { project, toolchain, .. } => {
+                write!(f, "test ({} files, {} bytes, {toolchain})", project.files.len(), project.len())
+            },
+            ClientMessage::Lint { project, toolchain } => {
+                write!(f, "lint ({} files, {} bytes, {toolchain})", project.files.len(), project.len())
+            },
+            ClientMessage::Inspect { project, target, toolchain, build } => {
+                write!(f, "inspect {target} ({} files, {} bytes, {toolchain}, {build})", project.files.len(), project.len())
+            },
+            ClientMessage::ListCrates => write!(f, "list_crates"),
+            ClientMessage::Share { snippet } => {
+                write!(f, "share ({} files, {} bytes)", snippet.project.files.len(), snippet.project.len())
+            },
+            ClientMessage::LoadSnippet { id } => write!(f, "load_snippet: {id}"),
+            ClientMessage::Format { source, edition } => write!(f, "format ({} bytes, edition {edition})", source.len()),
+            ClientMessage::Input { data } => write!(f, "input: {data:?}"),
+            ClientMessage::Exit => write!(f, "exit"),
+        }
+    }
+}
+
+//ServerMessage -------------------------------------------------------------
+/// Responses and events sent by the server to the playground client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Answer to [`ClientMessage::Hello`] when the versions match; `user` is
+    /// the login the session is tied to, if the client sent a token.
+    Welcome {
+        protocol_version: u32,
+        #[serde(default)]
+        user: Option<String>,
+    },
+    /// Every execution slot is taken: the client is waiting in line.
+    Queued { position: u32, estimated_wait_secs: u64 },
+    /// The client left the waiting queue and got an execution slot.
+    Admitted,
+    /// Human readable progress of the request (building, executing...).
+    Status { message: String },
+    /// Answer to [`ClientMessage::ListCrates`].
+    CrateList { crates: Vec<CrateInfo> },
+    /// Answer to [`ClientMessage::Share`]: the id the snippet can be loaded with.
+    Shared { id: String },
+    /// Answer to [`ClientMessage::LoadSnippet`].
+    Snippet { id: String, snippet: Snippet },
+    /// Answer to [`ClientMessage::Format`]: the formatted source.
+    Formatted { source: String },
+    /// Answer to [`ClientMessage::Format`] when rustfmt could not parse the
+    /// source; `line` and `column` (1-based) locate the error, when known.
+    FormatError {
+        message: String,
+        line: Option<u32>,
+        column: Option<u32>,
+    },
+    /// A compiler diagnostic, sent while the build is in progress.
+    Diagnostic { diagnostic: Diagnostic },
+    /// A lint found by [`ClientMessage::Lint`]; compile errors are still
+    /// sent as [`ServerMessage::Diagnostic`].
+    Lint { lint: LintItem },
+    /// What cargo printed besides the diagnostics; marks the end of the build.
+    CompilationResult { output: String },
+    /// Chunk of the program's stdout.
+    Stdout { data: String },
+    /// Chunk of the program's stderr.
+    Stderr { data: String },
+    /// Chunk of the output asked by [`ClientMessage::Inspect`]; one message
+    /// would not fit in a frame.
+    InspectOutput { target: InspectTarget, data: String },
+    /// The whole output asked by [`ClientMessage::Inspect`] was sent;
+    /// `truncated` if it was too large to be sent entirely.
+    InspectFinished { target: InspectTarget, truncated: bool },
+    /// A test ran by [`ClientMessage::Test`] is over.
+    TestResult { result: TestResult },
+    /// Every test ran by [`ClientMessage::Test`] is over; sent right before
+    /// [`ServerMessage::RunFinished`].
+    TestSummary { summary: TestSummary },
+    /// The program (or cargo command) is over; sent right before [`ServerMessage::Exit`].
+    RunFinished { reason: TerminationReason },
+    /// The server is going down: the request in progress can go on for
+    /// `grace_period_secs`, then it is stopped; no new request is accepted.
+    ShuttingDown { grace_period_secs: u64 },
+    /// The request was refused because the user hit one of its limits
+    /// (`message` says which); it can be sent again in `retry_after_secs`.
+    RateLimited { message: String, retry_after_secs: u64 },
+    /// The request failed on the server side.
+    Error { message: String },
+    /// The last client message could not be parsed.
+    RequestCorrupted { reason: String },
+    /// The server is done with the request and is closing the session.
+    Exit { reason: String },
+}
+
+impl ClientMessage {
+    pub fn hello() -> Self {
+        ClientMessage::Hello { protocol_version: crate::PROTOCOL_VERSION, token: None }
+    }
+
+    pub fn hello_with_token(token: impl Into<String>) -> Self {
+        ClientMessage::Hello { protocol_version: crate::PROTOCOL_VERSION, token: Some(token.into()) }
+    }
+}
+
+impl ServerMessage {
+    pub fn welcome(user: Option<String>) -> Self {
+        ServerMessage::Welcome { protocol_version: crate::PROTOCOL_VERSION, user }
+    }
+
+    pub fn status(message: impl Into<String>) -> Self {
+        ServerMessage::Status { message: message.into() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        ServerMessage::Error { message: message.into() }
+    }
+
+    pub fn exit(reason: impl Into<String>) -> Self {
+        ServerMessage::Exit { reason: reason.into() }
+    }
+}
+
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerMessage::Welcome { protocol_version, user: None } => write!(f, "welcome (v{protocol_version})"),
+            ServerMessage::Welcome { protocol_version, user: Some(user) } => write!(f, "welcome (v{protocol_version}): {user}"),
+            ServerMessage::Queued { position, estimated_wait_secs } => {
+                write!(f, "queued: position {position}, ~{estimated_wait_secs}s")
+            },
+            ServerMessage::Admitted => write!(f, "admitted"),
+            ServerMessage::Status { message } => write!(f, "status: {message}"),
+            ServerMessage::CrateList { crates } => write!(f, "crate_list ({} crates)", crates.len()),
+            ServerMessage::Shared { id } => write!(f, "shared: {id}"),
+            ServerMessage::Snippet { id, snippet } => {
+                write!(f, "snippet {id} ({} files, {} bytes)", snippet.project.files.len(), snippet.project.len())
+            },
+            ServerMessage::Formatted { source } => write!(f, "formatted ({} bytes)", source.len()),
+            ServerMessage::FormatError { message, line: Some(line), column: Some(column) } => {
+                write!(f, "format_error: {message} ({line}:{column})")
+            },
+            ServerMessage::FormatError { message, .. } => write!(f, "format_error: {message}"),
+            ServerMessage::Diagnostic { diagnostic } => write!(f, "diagnostic: {diagnostic}"),
+            ServerMessage::Lint { lint } => write!(f, "lint: {lint}"),
+            ServerMessage::CompilationResult { output } => write!(f, "compilation_result: {output}"),
+            ServerMessage::Stdout { data } => write!(f, "stdout: {data}"),
+            ServerMessage::Stderr { data } => write!(f, "stderr: {data}"),
+            ServerMessage::InspectOutput { target, data } => write!(f, "inspect_output: {target} ({} bytes)", data.len()),
+            ServerMessage::InspectFinished { target, truncated: false } => write!(f, "inspect_finished: {target}"),
+            ServerMessage::InspectFinished { target, truncated: true } => write!(f, "inspect_finished: {target} (truncated)"),
+            ServerMessage::TestResult { result } => write!(f, "test_result: {result}"),
+            ServerMessage::TestSummary { summary } => write!(f, "test_summary: {summary}"),
+            ServerMessage::RunFinished { reason } => write!(f, "run_finished: {reason}"),
+            ServerMessage::ShuttingDown { grace_period_secs } => write!(f, "shutting_down: {grace_period_secs}s left"),
+            ServerMessage::RateLimited { message, retry_after_secs } => {
+                write!(f, "rate_limited: {message} (retry in {retry_after_secs}s)")
+            },
+            ServerMessage::Error { message } => write!(f, "error: {message}"),
+            ServerMessage::RequestCorrupted { reason } => write!(f, "request_corrupted: {reason}"),
+            ServerMessage::Exit { reason } => write!(f, "exit: {reason}"),
+        }
+    }
+}
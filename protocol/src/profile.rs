@@ -0,0 +1,106 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// Cargo profile a project is built with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildProfile {
+    /// Fast to build, checks integer overflows.
+    #[default]
+    Debug,
+    Release,
+}
+
+/// `opt-level` of a cargo profile.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    #[serde(rename = "0")]
+    O0,
+    #[serde(rename = "1")]
+    O1,
+    #[serde(rename = "2")]
+    O2,
+    #[serde(rename = "3")]
+    O3,
+    /// Optimise for size.
+    #[serde(rename = "s")]
+    S,
+    /// Optimise for size, without loop vectorisation.
+    #[serde(rename = "z")]
+    Z,
+}
+
+/// How a run builds the project: its profile, and the settings overriding
+/// the ones of the profile.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BuildOptions {
+    #[serde(default)]
+    pub profile: BuildProfile,
+    /// `None` keeps the profile's own (on in debug, off in release).
+    #[serde(default)]
+    pub overflow_checks: Option<bool>,
+    /// `None` keeps the profile's own (0 in debug, 3 in release).
+    #[serde(default)]
+    pub opt_level: Option<OptLevel>,
+}
+
+impl BuildProfile {
+    pub const ALL: [BuildProfile; 2] = [BuildProfile::Debug, BuildProfile::Release];
+
+    /// Also the directory of the profile in the target directory.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildProfile::Debug => "debug",
+            BuildProfile::Release => "release",
+        }
+    }
+}
+
+impl OptLevel {
+    pub const ALL: [OptLevel; 6] = [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::O3, OptLevel::S, OptLevel::Z];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+            OptLevel::O3 => "3",
+            OptLevel::S => "s",
+            OptLevel::Z => "z",
+        }
+    }
+
+    /// Value of `opt-level` in a `Cargo.toml`: numbers are not quoted.
+    pub fn to_toml(&self) -> String {
+        match self {
+            OptLevel::S | OptLevel::Z => format!("\"{}\"", self.as_str()),
+            _ => self.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BuildProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for BuildOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.profile)?;
+        if let Some(opt_level) = self.opt_level {
+            write!(f, ", opt-level {opt_level}")?;
+        }
+        match self.overflow_checks {
+            Some(true) => write!(f, ", overflow checks on"),
+            Some(false) => write!(f, ", overflow checks off"),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashSet, fmt};
+use serde::{Serialize, Deserialize};
+
+/// Most files a project can have.
+pub const MAX_PROJECT_FILES: usize = 32;
+/// Biggest single file, in bytes.
+pub const MAX_FILE_LEN: usize = 32 * 1024;
+/// Biggest project (sum of every file), in bytes; keeps requests well within
+/// [`crate::MAX_FRAME_LEN`].
+pub const MAX_PROJECT_LEN: usize = 64 * 1024;
+/// Longest accepted path.
+pub const MAX_PATH_LEN: usize = 128;
+/// Most dependencies a project can declare.
+pub const MAX_DEPENDENCIES: usize = 16;
+
+/// Folders a project file can live in; anything at the root of the crate
+/// (`Cargo.toml`, `build.rs`, ...) is written by the server.
+pub const PROJECT_DIRS: [&str; 4] = ["src", "tests", "examples", "benches"];
+/// Extensions a project file can have.
+pub const PROJECT_EXTENSIONS: [&str; 6] = ["rs", "txt", "md", "json", "toml", "csv"];
+
+pub const MAIN_PATH: &str = "src/main.rs";
+pub const LIB_PATH: &str = "src/lib.rs";
+
+/// A file of a [`Project`], with its path relative to the crate root
+/// (e.g. `src/utils/mod.rs`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFile {
+    pub path: String,
+    pub contents: String,
+}
+
+/// The sources of a small Cargo package; the server generates the manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Project {
+    pub files: Vec<ProjectFile>,
+    /// Names of the crates the project uses, among the ones listed by
+    /// [`crate::ServerMessage::CrateList`].
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+/// Rust edition code is compiled (or formatted) with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[default]
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+/// A crate projects can depend on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CrateInfo {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProjectError {
+    #[error("the project has {count} files, at most {max} are allowed")]
+    TooManyFiles { count: usize, max: usize },
+    #[error("'{path}' is {len} bytes, at most {max} are allowed")]
+    FileTooLarge { path: String, len: usize, max: usize },
+    #[error("the project is {len} bytes, at most {max} are allowed")]
+    ProjectTooLarge { len: usize, max: usize },
+    #[error("invalid path '{path}': {reason}")]
+    InvalidPath { path: String, reason: &'static str },
+    #[error("'{0}' appears more than once")]
+    DuplicatePath(String),
+    #[error("the project needs a '{MAIN_PATH}' or a '{LIB_PATH}'")]
+    MissingEntryPoint,
+    #[error("the project has {count} dependencies, at most {max} are allowed")]
+    TooManyDependencies { count: usize, max: usize },
+    #[error("dependency '{0}' appears more than once")]
+    DuplicateDependency(String),
+}
+
+impl Edition {
+    pub const ALL: [Edition; 4] = [Edition::E2015, Edition::E2018, Edition::E2021, Edition::E2024];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ProjectFile {
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        ProjectFile { path: path.into(), contents: contents.into() }
+    }
+}
+
+impl Project {
+    /// A binary crate made of `src/main.rs` only.
+    pub fn single(source: impl Into<String>) -> Self {
+        Project { files: vec![ProjectFile::new(MAIN_PATH, source)], dependencies: Vec::new() }
+    }
+
+    pub fn file(&self, path: &str) -> Option<&ProjectFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    pub fn has_binary(&self) -> bool {
+        self.file(MAIN_PATH).is_some()
+    }
+
+    pub fn has_library(&self) -> bool {
+        self.file(LIB_PATH).is_some()
+    }
+
+    /// Sum of the size of every file, in bytes.
+    pub fn len(&self) -> usize {
+        self.files.iter().map(|file| file.contents.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Checks the limits and that every path can be written safely inside
+    /// the crate folder.
+    pub fn validate(&self) -> Result<(), ProjectError> {
+        if self.files.len() > MAX_PROJECT_FILES {
+            return Err(ProjectError::TooManyFiles { count: self.files.len(), max: MAX_PROJECT_FILES });
+        }
+
+        let mut paths = HashSet::new();
+        for file in &self.files {
+            validate_path(&file.path)?;
+
+            if file.contents.len() > MAX_FILE_LEN {
+                return Err(ProjectError::FileTooLarge {
+                    path: file.path.clone(),
+                    len: file.contents.len(),
+                    max: MAX_FILE_LEN,
+                });
+            }
+            if !paths.insert(file.path.as_str()) {
+                return Err(ProjectError::DuplicatePath(file.path.clone()));
+            }
+        }
+
+        if self.len() > MAX_PROJECT_LEN {
+            return Err(ProjectError::ProjectTooLarge { len: self.len(), max: MAX_PROJECT_LEN });
+        }
+        if !self.has_binary() && !self.has_library() {
+            return Err(ProjectError::MissingEntryPoint);
+        }
+
+        if self.dependencies.len() > MAX_DEPENDENCIES {
+            return Err(ProjectError::TooManyDependencies { count: self.dependencies.len(), max: MAX_DEPENDENCIES });
+        }
+        let mut dependencies = HashSet::new();
+        for dependency in &self.dependencies {
+            if !dependencies.insert(dependency.as_str()) {
+                return Err(ProjectError::DuplicateDependency(dependency.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Accepts `<dir>/<name>/.../<name>.<ext>` where `dir` is one of
+/// [`PROJECT_DIRS`], every name is made of ASCII letters, digits, `_` and `-`
+/// and `ext` is one of [`PROJECT_EXTENSIONS`].
+///
+/// Valid paths need no quoting in a shell and can't leave the crate folder.
+pub fn validate_path(path: &str) -> Result<(), ProjectError> {
+    let invalid = |reason| Err(ProjectError::InvalidPath { path: path.to_string(), reason });
+
+    if path.len() > MAX_PATH_LEN {
+        return invalid("too long");
+    }
+
+    let segments: Vec<&str> = path.split('/').collect();
+    let Some((file_name, dirs)) = segments.split_last() else {
+        return invalid("empty");
+    };
+
+    match dirs.first() {
+        Some(dir) if PROJECT_DIRS.contains(dir) => {},
+        _ => return invalid("files must be inside src/, tests/, examples/ or benches/"),
+    }
+
+    let is_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    if !dirs.iter().all(|dir| is_name(dir)) {
+        return invalid("folder names can only contain letters, digits, '_' and '-'");
+    }
+
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if is_name(stem) && PROJECT_EXTENSIONS.contains(&extension) => Ok(()),
+        Some((stem, _)) if is_name(stem) => invalid("unsupported file extension"),
+        _ => invalid("file names can only contain letters, digits, '_' and '-', plus an extension"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_sanitised() {
+        for path in ["src/main.rs", "src/utils/mod.rs", "tests/it-works.rs", "src/data/input_1.txt"] {
+            assert_eq!(validate_path(path), Ok(()), "{path}");
+        }
+
+        for path in [
+            "", "main.rs", "Cargo.toml", "build.rs", "/src/main.rs", "src//main.rs", "src/../main.rs",
+            "src/./main.rs", "../src/main.rs", "src/.hidden.rs", "src/main", "src/main.exe",
+            "src/ma in.rs", "src/main.rs;rm", "src\\main.rs", "target/main.rs",
+        ] {
+            assert!(validate_path(path).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn projects_are_limited() {
+        assert_eq!(Project::single("fn main() {}").validate(), Ok(()));
+        assert_eq!(Project::default().validate(), Err(ProjectError::MissingEntryPoint));
+
+        let mut project = Project { files: vec![ProjectFile::new(LIB_PATH, "")], dependencies: vec!["rand".to_string()] };
+        assert_eq!(project.validate(), Ok(()));
+
+        project.dependencies.push("rand".to_string());
+        assert_eq!(project.validate(), Err(ProjectError::DuplicateDependency("rand".to_string())));
+
+        project.files.push(ProjectFile::new(LIB_PATH, ""));
+        assert_eq!(project.validate(), Err(ProjectError::DuplicatePath(LIB_PATH.to_string())));
+
+        let project = Project::single("a".repeat(MAX_FILE_LEN + 1));
+        assert!(matches!(project.validate(), Err(ProjectError::FileTooLarge { .. })));
+
+        let project = Project {
+            files: (0..3).map(|i| ProjectFile::new(format!("src/m{i}.rs"), "a".repeat(MAX_FILE_LEN))).collect(),
+            dependencies: Vec::new(),
+        };
+        assert!(matches!(project.validate(), Err(ProjectError::ProjectTooLarge { .. })));
+
+        let project = Project {
+            files: (0..=MAX_PROJECT_FILES).map(|i| ProjectFile::new(format!("src/m{i}.rs"), "")).collect(),
+            dependencies: Vec::new(),
+        };
+        assert!(matches!(project.validate(), Err(ProjectError::TooManyFiles { .. })));
+    }
+}
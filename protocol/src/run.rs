@@ -0,0 +1,55 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// Limits requested by the client for one run.
+///
+/// Every field is optional: missing values fall back to the server defaults
+/// and values above the server maximums are clamped.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    pub wall_time_secs: Option<u64>,
+    pub cpu_time_secs: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub max_processes: Option<u64>,
+    pub max_output_bytes: Option<u64>,
+}
+
+/// Why a program stopped running.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// The program returned on its own.
+    Exited { code: i32 },
+    /// The program was killed by a signal it raised or received (e.g. abort).
+    Signaled { signal: i32 },
+    /// Wall-clock timeout hit.
+    TimedOut,
+    /// CPU time limit hit.
+    CpuTimeExceeded,
+    /// The program could not allocate within its memory limit.
+    OutOfMemory,
+    /// The program printed more than allowed.
+    OutputLimitExceeded,
+    /// The client asked to stop the program.
+    KilledByUser,
+}
+
+impl TerminationReason {
+    pub fn is_success(&self) -> bool {
+        matches!(self, TerminationReason::Exited { code: 0 })
+    }
+}
+
+impl fmt::Display for TerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminationReason::Exited { code } => write!(f, "process exited with code {code}"),
+            TerminationReason::Signaled { signal } => write!(f, "process killed by signal {signal}"),
+            TerminationReason::TimedOut => write!(f, "time limit exceeded"),
+            TerminationReason::CpuTimeExceeded => write!(f, "CPU time limit exceeded"),
+            TerminationReason::OutOfMemory => write!(f, "memory limit exceeded"),
+            TerminationReason::OutputLimitExceeded => write!(f, "output limit exceeded"),
+            TerminationReason::KilledByUser => write!(f, "stopped by the user"),
+        }
+    }
+}
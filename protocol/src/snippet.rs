@@ -0,0 +1,72 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{BuildOptions, Project, ProjectError, Toolchain};
+
+/// Length of the id of a shared snippet.
+pub const SNIPPET_ID_LEN: usize = 10;
+/// Longest author name a snippet can carry.
+pub const MAX_AUTHOR_LEN: usize = 64;
+
+/// A project shared with [`crate::ClientMessage::Share`], with the settings
+/// it runs with. Once shared, it never changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snippet {
+    pub project: Project,
+    #[serde(default)]
+    pub toolchain: Toolchain,
+    #[serde(default)]
+    pub build: BuildOptions,
+    /// Login of the account that shared it, if the author was logged in.
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnippetError {
+    #[error(transparent)]
+    Project(#[from] ProjectError),
+    #[error("the author name is {len} bytes, at most {max} are allowed")]
+    AuthorTooLong { len: usize, max: usize },
+}
+
+impl Snippet {
+    /// Checks the limits of the project (see [`Project::validate`]) and of
+    /// the author name.
+    pub fn validate(&self) -> Result<(), SnippetError> {
+        self.project.validate()?;
+
+        match &self.author {
+            Some(author) if author.len() > MAX_AUTHOR_LEN => {
+                Err(SnippetError::AuthorTooLong { len: author.len(), max: MAX_AUTHOR_LEN })
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether `id` can be the id of a snippet: [`SNIPPET_ID_LEN`] ASCII letters
+/// and digits, nothing that could name a path.
+pub fn is_snippet_id(id: &str) -> bool {
+    id.len() == SNIPPET_ID_LEN && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_are_limited() {
+        let mut snippet = Snippet { project: Project::single("fn main() {}"), ..Default::default() };
+        assert_eq!(snippet.validate(), Ok(()));
+
+        snippet.author = Some("a".repeat(MAX_AUTHOR_LEN + 1));
+        assert_eq!(snippet.validate(), Err(SnippetError::AuthorTooLong { len: MAX_AUTHOR_LEN + 1, max: MAX_AUTHOR_LEN }));
+
+        snippet.project = Project::default();
+        assert_eq!(snippet.validate(), Err(SnippetError::Project(ProjectError::MissingEntryPoint)));
+
+        assert!(is_snippet_id("aZ09bY18cX"));
+        assert!(!is_snippet_id("../../etc/"));
+        assert!(!is_snippet_id("short"));
+    }
+}
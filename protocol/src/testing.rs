@@ -0,0 +1,69 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// Outcome of a single `#[test]` function.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// Path of the test inside its crate, e.g. `tests::adds_two`.
+    pub name: String,
+    /// File of the test target, e.g. `src/lib.rs` or `tests/integration.rs`.
+    pub suite: String,
+    pub status: TestStatus,
+    /// `None` for ignored tests.
+    pub duration_micros: Option<u64>,
+    /// What the test printed (stdout and stderr), including the panic message.
+    #[serde(default)]
+    pub output: String,
+}
+
+/// Totals of a test run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+}
+
+impl TestSummary {
+    pub fn add(&mut self, status: TestStatus) {
+        match status {
+            TestStatus::Passed => self.passed += 1,
+            TestStatus::Failed => self.failed += 1,
+            TestStatus::Ignored => self.ignored += 1,
+        }
+    }
+}
+
+impl fmt::Display for TestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestStatus::Passed => write!(f, "ok"),
+            TestStatus::Failed => write!(f, "FAILED"),
+            TestStatus::Ignored => write!(f, "ignored"),
+        }
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "test {} ({}) ... {}", self.name, self.suite, self.status)?;
+        if let Some(micros) = self.duration_micros {
+            write!(f, " <{:.3}s>", micros as f64 / 1_000_000.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TestSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} passed; {} failed; {} ignored", self.passed, self.failed, self.ignored)
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use playground_protocol::{verify_token, SessionClaims, TokenError, MIN_KEY_LEN};
+
+use crate::config::{AuthConfig, ConfigError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("a session token is required, log in first")]
+    Missing,
+    #[error("{0}, log in again")]
+    Token(#[from] TokenError),
+}
+
+/// Checks the session tokens sent with the handshake, signed by the account
+/// backend of the app with the key shared with the server.
+pub struct Authenticator {
+    key: Option<Vec<u8>>,
+    required: bool,
+}
+
+impl Authenticator {
+    /// Reads the key named by `config`, if any.
+    pub fn new(config: &AuthConfig) -> Result<Self, ConfigError> {
+        let key = match &config.key_file {
+            Some(path) => {
+                let key = std::fs::read(path)
+                    .map_err(|source| ConfigError::Read { path: path.clone(), source })?;
+                let key = key.trim_ascii().to_vec();//keys are usually written with a newline
+
+                if key.len() < MIN_KEY_LEN {
+                    return Err(ConfigError::Invalid {
+                        key: "auth.key_file",
+                        reason: format!("the key is {} bytes, at least {MIN_KEY_LEN} are needed", key.len()),
+                    });
+                }
+                Some(key)
+            },
+            None => None,
+        };
+
+        Ok(Authenticator { key, required: config.required })
+    }
+
+    /// The account `token` was issued to, or `None` for an anonymous session.
+    ///
+    /// Without a key the tokens can't be checked, and every session is anonymous.
+    pub fn authenticate(&self, token: Option<&str>) -> Result<Option<SessionClaims>, AuthError> {
+        let Some(key) = &self.key else {
+            return Ok(None);
+        };
+
+        match token {
+            Some(token) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                Ok(Some(verify_token(token, key, now)?))
+            },
+            None if self.required => Err(AuthError::Missing),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::sign_token;
+
+    use super::*;
+
+    #[test]
+    fn sessions_are_tied_to_valid_tokens() {
+        let key = b"0123456789abcdef0123456789abcdef".to_vec();
+        let token = sign_token(&SessionClaims { login: "mario.rossi".to_string(), role: None, expires_at: u64::MAX }, &key);
+        let expired = sign_token(&SessionClaims { login: "mario.rossi".to_string(), role: None, expires_at: 1 }, &key);
+
+        let optional = Authenticator { key: Some(key.clone()), required: false };
+        assert_eq!(optional.authenticate(Some(&token)).unwrap().map(|claims| claims.login), Some("mario.rossi".to_string()));
+        assert!(optional.authenticate(None).unwrap().is_none());
+        assert!(matches!(optional.authenticate(Some(&expired)), Err(AuthError::Token(TokenError::Expired))));
+
+        let required = Authenticator { key: Some(key), required: true };
+        assert!(matches!(required.authenticate(None), Err(AuthError::Missing)));
+
+        let disabled = Authenticator { key: None, required: false };
+        assert!(disabled.authenticate(Some("anything")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use std::{path::Path, process::Stdio};
+
+use playground_protocol::{Applicability, Diagnostic, DiagnosticLevel, DiagnosticSpan};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Command,
+};
+
+use super::{libtest::TestBinary, BackendError, BuildOutput, DiagnosticSender};
+
+/// Most bytes of [`BuildOutput::emitted`] kept.
+pub const MAX_EMITTED_LEN: usize = 4 * 1024 * 1024;
+
+/// One line of `cargo build --message-format=json`.
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CargoMessage {
+    CompilerMessage { message: RustcDiagnostic },
+    CompilerArtifact {
+        manifest_path: String,
+        target: CargoTarget,
+        profile: CargoProfile,
+        executable: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct CargoTarget {
+    src_path: String,
+}
+
+#[derive(Deserialize)]
+struct CargoProfile {
+    test: bool,
+}
+
+#[derive(Deserialize)]
+struct RustcDiagnostic {
+    message: String,
+    code: Option<RustcCode>,
+    level: String,
+    spans: Vec<RustcSpan>,
+    children: Vec<RustcDiagnostic>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Runs a `cargo build --message-format=json` command (or any cargo command
+/// building with that format), sending every compiler diagnostic as soon as
+/// cargo prints it and collecting the test binaries it builds, and what
+/// rustc printed on stdout.
+///
+/// The command must run from the root of the client's crate, so the spans
+/// point to the paths of the project files.
+pub async fn cargo_build(mut command: Command, diagnostics: DiagnosticSender) -> Result<BuildOutput, BackendError> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(BackendError::io("ERR_PLAYGROUND_CARGORUSTC"))?;
+
+    let (Some(stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(BackendError::Command {
+            context: "ERR_PLAYGROUND_CARGORUSTC",
+            detail: "cargo's output pipes are missing".to_string(),
+        });
+    };
+
+    let mut tests = Vec::new();
+    let mut emitted = String::new();
+    let mut emitted_truncated = false;
+    let forward_diagnostics = async {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            //every message of cargo starts with its "reason": the rest comes from rustc
+            if !line.starts_with("{\"reason\":") {
+                if emitted.len() + line.len() < MAX_EMITTED_LEN {
+                    emitted.push_str(&line);
+                    emitted.push('\n');
+                } else {
+                    emitted_truncated = true;
+                }
+            } else if let Some(diagnostic) = parse_line(&line) {
+                //the receiver is gone only if the build is being aborted
+                let _ = diagnostics.send(diagnostic);
+            } else if let Some(binary) = test_binary(&line) {
+                tests.push(binary);
+            }
+        }
+    };
+    let mut log = String::new();
+    let read_log = stderr.read_to_string(&mut log);
+
+    let (_, read) = tokio::join!(forward_diagnostics, read_log);
+    read.map_err(BackendError::io("ERR_PLAYGROUND_CARGORUSTC"))?;
+
+    let status = child.wait()
+        .await
+        .map_err(BackendError::io("ERR_PLAYGROUND_CARGORUSTC"))?;
+
+    Ok(BuildOutput { status, log, tests, emitted, emitted_truncated })
+}
+
+/// Returns the test binary announced by a line of cargo's JSON output, if any.
+fn test_binary(line: &str) -> Option<TestBinary> {
+    match serde_json::from_str(line) {
+        Ok(CargoMessage::CompilerArtifact { manifest_path, target, profile, executable: Some(path) }) if profile.test => {
+            Some(TestBinary { suite: relative_path(&target.src_path, &manifest_path), path })
+        },
+        _ => None,
+    }
+}
+
+/// `path` relative to the folder of the manifest, e.g. `src/lib.rs`.
+fn relative_path(path: &str, manifest_path: &str) -> String {
+    let root = Path::new(manifest_path).parent().unwrap_or(Path::new(""));
+    Path::new(path).strip_prefix(root).unwrap_or(Path::new(path)).to_string_lossy().to_string()
+}
+
+/// Returns the diagnostic carried by a line of cargo's JSON output, if any.
+pub fn parse_line(line: &str) -> Option<Diagnostic> {
+    match serde_json::from_str(line) {
+        Ok(CargoMessage::CompilerMessage { message }) => Some(message.into_diagnostic()),
+        _ => None,
+    }
+}
+
+impl RustcDiagnostic {
+    fn into_diagnostic(self) -> Diagnostic {
+        let level = match self.level.as_str() {
+            "error" => DiagnosticLevel::Error,
+            "warning" => DiagnosticLevel::Warning,
+            "note" => DiagnosticLevel::Note,
+            "help" => DiagnosticLevel::Help,
+            "failure-note" => DiagnosticLevel::FailureNote,
+            _ => DiagnosticLevel::Ice,
+        };
+
+        Diagnostic {
+            level,
+            code: self.code.map(|code| code.code),
+            message: self.message,
+            spans: self.spans.into_iter().map(RustcSpan::into_span).collect(),
+            children: self.children.into_iter().map(RustcDiagnostic::into_diagnostic).collect(),
+            rendered: self.rendered,
+        }
+    }
+}
+
+impl RustcSpan {
+    fn into_span(self) -> DiagnosticSpan {
+        let suggestion_applicability = self.suggestion_applicability.map(|applicability| {
+            match applicability.as_str() {
+                "MachineApplicable" => Applicability::MachineApplicable,
+                "MaybeIncorrect" => Applicability::MaybeIncorrect,
+                "HasPlaceholders" => Applicability::HasPlaceholders,
+                _ => Applicability::Unspecified,
+            }
+        });
+
+        DiagnosticSpan {
+            file_name: self.file_name,
+            byte_start: self.byte_start,
+            byte_end: self.byte_end,
+            line_start: self.line_start,
+            line_end: self.line_end,
+            column_start: self.column_start,
+            column_end: self.column_end,
+            is_primary: self.is_primary,
+            label: self.label,
+            suggested_replacement: self.suggested_replacement,
+            suggestion_applicability,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compiler_messages_only() {
+        let line = r#"{"reason":"compiler-message","package_id":"playground 0.1.0","manifest_path":"Cargo.toml","target":{"name":"x"},"message":{"rendered":"warning: unused variable: `x`\n --> src/main.rs:2:9\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":21,"byte_start":20,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_x","suggestion_applicability":"MachineApplicable","text":[]}]}],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":21,"byte_start":20,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+        let diagnostic = parse_line(line).unwrap();
+        assert_eq!(diagnostic.level, DiagnosticLevel::Warning);
+        assert_eq!(diagnostic.code.as_deref(), Some("unused_variables"));
+        assert_eq!(diagnostic.spans[0].file_name, "src/main.rs");
+        assert_eq!(diagnostic.rendered.as_deref(), Some("warning: unused variable: `x`\n --> src/main.rs:2:9\n"));
+        assert_eq!(diagnostic.machine_applicable_fixes()[0].suggested_replacement.as_deref(), Some("_x"));
+
+        assert!(parse_line(r#"{"reason":"build-finished","success":true}"#).is_none());
+        assert!(test_binary(line).is_none());
+    }
+
+    #[test]
+    fn finds_test_binaries() {
+        let line = r#"{"reason":"compiler-artifact","package_id":"playground 0.1.0","manifest_path":"/app/sessions/1/Cargo.toml","target":{"kind":["test"],"crate_types":["bin"],"name":"it","src_path":"/app/sessions/1/tests/it.rs","edition":"2024","doc":false,"doctest":false,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":true},"features":[],"filenames":["/app/compile/target/debug/deps/it-1a2b"],"executable":"/app/compile/target/debug/deps/it-1a2b","fresh":false}"#;
+        assert_eq!(test_binary(line), Some(TestBinary {
+            suite: "tests/it.rs".to_string(),
+            path: "/app/compile/target/debug/deps/it-1a2b".to_string(),
+        }));
+
+        //the library itself, not its tests
+        let line = line.replace(r#""overflow_checks":true,"test":true"#, r#""overflow_checks":true,"test":false"#);
+        assert!(test_binary(&line).is_none());
+        assert!(parse_line("   Compiling playground v0.1.0").is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use playground_protocol::{CrateInfo, Diagnostic, DiagnosticLevel};
+
+/// A crate projects can depend on.
+pub struct AvailableCrate {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub features: &'static [&'static str],
+    pub description: &'static str,
+}
+
+/// Crates projects can depend on.
+///
+/// Must match `docker/compiler/Cargo.toml`: the COMPILER image builds them
+/// once, and every session starts from a copy of them.
+pub const AVAILABLE_CRATES: &[AvailableCrate] = &[
+    AvailableCrate { name: "anyhow", version: "1.0.98", features: &[], description: "Flexible concrete error type" },
+    AvailableCrate { name: "clap", version: "4.5.38", features: &["derive"], description: "Command line argument parser" },
+    AvailableCrate { name: "env_logger", version: "0.11.8", features: &[], description: "Logger configured through environment variables" },
+    AvailableCrate { name: "indicatif", version: "0.17.11", features: &[], description: "Progress bars and spinners" },
+    AvailableCrate { name: "log", version: "0.4.27", features: &[], description: "Logging facade" },
+    AvailableCrate { name: "rand", version: "0.9.0", features: &[], description: "Random number generators" },
+    AvailableCrate { name: "reqwest", version: "0.12.15", features: &["blocking"], description: "HTTP client" },
+    AvailableCrate { name: "serde", version: "1.0.219", features: &["derive"], description: "Serialization framework" },
+    AvailableCrate { name: "serde_json", version: "1.0.140", features: &[], description: "JSON support for serde" },
+    AvailableCrate { name: "thiserror", version: "2.0.12", features: &[], description: "derive(Error)" },
+    AvailableCrate { name: "tokio", version: "1.45.0", features: &["full"], description: "Asynchronous runtime" },
+];
+
+#[derive(Debug, thiserror::Error)]
+#[error("crate '{0}' is not available (see the list of available crates)")]
+pub struct UnknownCrate(pub String);
+
+pub fn find(name: &str) -> Option<&'static AvailableCrate> {
+    AVAILABLE_CRATES.iter().find(|krate| krate.name == name)
+}
+
+pub fn crate_list() -> Vec<CrateInfo> {
+    AVAILABLE_CRATES
+        .iter()
+        .map(|krate| CrateInfo {
+            name: krate.name.to_string(),
+            version: krate.version.to_string(),
+            description: krate.description.to_string(),
+        })
+        .collect()
+}
+
+/// Checks that every dependency is whitelisted.
+pub fn check_dependencies(dependencies: &[String]) -> Result<(), UnknownCrate> {
+    match dependencies.iter().find(|name| find(name).is_none()) {
+        Some(name) => Err(UnknownCrate(name.clone())),
+        None => Ok(()),
+    }
+}
+
+/// `[dependencies]` entries of the given (whitelisted) crates.
+pub fn dependency_lines(dependencies: &[String]) -> String {
+    dependencies
+        .iter()
+        .filter_map(|name| find(name))
+        .map(|krate| match krate.features {
+            [] => format!("{} = \"{}\"\n", krate.name, krate.version),
+            features => format!(
+                "{} = {{ version = \"{}\", features = [{}] }}\n",
+                krate.name,
+                krate.version,
+                features.iter().map(|feature| format!("\"{feature}\"")).collect::<Vec<_>>().join(", ")
+            ),
+        })
+        .collect()
+}
+
+/// Explains unresolved imports of crates (E0432/E0433) that the project
+/// didn't declare, adding a help to `diagnostic`.
+pub fn add_dependency_hint(diagnostic: &mut Diagnostic, dependencies: &[String]) {
+    if !matches!(diagnostic.code.as_deref(), Some("E0432" | "E0433")) {
+        return;
+    }
+
+    //e.g. "failed to resolve: use of unresolved module or unlinked crate `rand`"
+    let Some(name) = diagnostic.message
+        .split('`')
+        .nth(1)
+        .and_then(|path| path.split("::").next())
+    else {
+        return;
+    };
+    if dependencies.iter().any(|dependency| dependency == name) {
+        return;
+    }
+
+    let help = match find(name) {
+        Some(krate) => format!("`{name}` is available ({}): add it to the project's dependencies", krate.version),
+        None if diagnostic.message.contains("unlinked crate") || diagnostic.message.contains("undeclared crate") => {
+            format!("`{name}` is not among the available crates")
+        },
+        None => return,
+    };
+
+    if let Some(rendered) = &mut diagnostic.rendered {
+        rendered.push_str(&format!("help: {help}\n"));
+    }
+    diagnostic.children.push(Diagnostic {
+        level: DiagnosticLevel::Help,
+        code: None,
+        message: help,
+        spans: Vec::new(),
+        children: Vec::new(),
+        rendered: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unresolved(message: &str) -> Diagnostic {
+        Diagnostic {
+            level: DiagnosticLevel::Error,
+            code: Some("E0433".to_string()),
+            message: message.to_string(),
+            spans: Vec::new(),
+            children: Vec::new(),
+            rendered: None,
+        }
+    }
+
+    #[test]
+    fn hints_undeclared_crates() {
+        let mut diagnostic = unresolved("failed to resolve: use of unresolved module or unlinked crate `rand`");
+        add_dependency_hint(&mut diagnostic, &[]);
+        assert!(diagnostic.children[0].message.contains("add it to the project's dependencies"));
+
+        let mut diagnostic = unresolved("failed to resolve: use of unresolved module or unlinked crate `rand`");
+        add_dependency_hint(&mut diagnostic, &["rand".to_string()]);
+        assert!(diagnostic.children.is_empty());
+
+        let mut diagnostic = unresolved("failed to resolve: use of unresolved module or unlinked crate `regex`");
+        add_dependency_hint(&mut diagnostic, &[]);
+        assert!(diagnostic.children[0].message.contains("not among the available crates"));
+
+        let mut diagnostic = unresolved("failed to resolve: could not find `utils` in the crate root");
+        add_dependency_hint(&mut diagnostic, &[]);
+        assert!(diagnostic.children.is_empty());
+    }
+
+    #[test]
+    fn writes_dependency_lines() {
+        assert!(check_dependencies(&["rand".to_string(), "regex".to_string()]).is_err());
+        assert_eq!(
+            dependency_lines(&["rand".to_string(), "tokio".to_string()]),
+            "rand = \"0.9.0\"\ntokio = { version = \"1.45.0\", features = [\"full\"] }\n"
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    process::{ExitStatus, Stdio},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use playground_protocol::{
+    BuildOptions, BuildProfile, CargoCommand, Channel, Edition, InspectTarget, Project, TerminationReason, Toolchain,
+};
+use tokio::{io::AsyncWriteExt, process::{Child, Command}};
+use uuid::Uuid;
+
+use super::{
+    cargo_messages::cargo_build, inspect, libtest::TEST_HARNESS_ARGS, limits::BUILD_TIMEOUT, manifest::{manifest, profile_args}, rustfmt::rustfmt,
+    rustup::ChannelCache, signal_reason, BackendError, BuildOutput, DiagnosticSender, ExecutionBackend, FormatOutput, ResourceLimits,
+    RetryPolicy, TestBinary, TestSelection,
+};
+
+pub const BUILDER_CONTAINER_NAME: &str = "ruscompy";
+pub const RUNNER_CONTAINER_NAME: &str = "ruruny";
+/// The programs of every session run in the RUNNER with a uid of their own,
+/// from this one up: the process limit (RLIMIT_NPROC) is counted per uid, and
+/// a session can't signal the processes of another.
+const FIRST_SESSION_UID: u32 = 20_000;
+/// Where the pid files of the cargo commands are kept, writable by root only.
+const PID_DIR: &str = "/run/playground";
+
+/// Crate of the COMPILER image: its lock file pins the versions of the
+/// available crates (see [`super::crates`]) for every session.
+const COMPILE_DIR: &str = "/app/compile";
+/// Dependencies built with the COMPILER image: copied into the target dir of
+/// every session, so they're built only once and no session waits on the
+/// build lock of another. Never built into.
+const SEED_TARGET_DIR: &str = "/app/compile/target";
+/// Parent folder of the sessions' crates.
+const SESSIONS_DIR: &str = "/app/sessions";
+
+/// Builds inside the COMPILER container and runs inside the RUNNER container
+/// (see `docker/docker-compose.yaml`); executables travel through the shared
+/// volume.
+///
+/// Every session gets its own crate, `session-<id>`, in [`SESSIONS_DIR`].
+pub struct DockerBackend {
+    builder: String,
+    runner: String,
+    retry: RetryPolicy,
+    channels: ChannelCache,
+    uids: Mutex<HashMap<Uuid, u32>>,//of the sessions that ran something, until cleaned
+}
+
+impl DockerBackend {
+    pub fn new(builder: &str, runner: &str) -> Self {
+        DockerBackend {
+            builder: builder.to_string(),
+            runner: runner.to_string(),
+            retry: RetryPolicy::default(),
+            channels: ChannelCache::default(),
+            uids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_retry(self, retry: RetryPolicy) -> Self {
+        DockerBackend { retry, ..self }
+    }
+
+    /// The uid the programs of session `id` run with, the lowest free one on
+    /// its first run.
+    fn session_uid(&self, id: Uuid) -> u32 {
+        let mut uids = self.uids.lock().unwrap();
+        if let Some(uid) = uids.get(&id) {
+            return *uid;
+        }
+
+        let uid = (FIRST_SESSION_UID..).find(|uid| !uids.values().any(|used| used == uid)).unwrap();
+        uids.insert(id, uid);
+        uid
+    }
+
+    /// Kills every process of the uid of session `id` in the RUNNER, even the
+    /// ones that left the process group of the program.
+    async fn kill_session_processes(&self, id: Uuid) -> Result<(), BackendError> {
+        let Some(uid) = self.uids.lock().unwrap().get(&id).copied() else {
+            return Ok(());//nothing ran
+        };
+
+        //'kill -1' as the uid: every process it can signal, but itself
+        let script = format!("setpriv {} sh -c 'kill -9 -1 2>/dev/null; true'", setpriv_args(uid));
+        self.exec(&self.runner, &script, "ERR_PLAYGROUND_KILL_EXEC").await
+    }
+
+    /// `docker exec <container> sh -c <script>`, waiting for it to finish.
+    async fn exec(&self, container: &str, script: &str, context: &'static str) -> Result<(), BackendError> {
+        tracing::debug!(container, script, "docker exec");
+        let output = Command::new("docker")
+            .args(["exec", container, "sh", "-c", script])
+            .output()
+            .await
+            .map_err(BackendError::io(context))?;
+
+        if !output.status.success() {
+            return Err(BackendError::Command {
+                context,
+                detail: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Writes `contents` to `file_path` in `container`, creating its folder.
+    ///
+    /// `file_path` is used in a shell script: it must not need quoting.
+    async fn write_file(&self, container: &str, file_path: &str, contents: &str) -> Result<(), BackendError> {
+        let mut child = Command::new("docker")
+            .args([
+                "exec",
+                "-i",//necessary when using pipes
+                container,
+                "sh",
+                "-c",
+                &format!("mkdir -p \"$(dirname {file_path})\" && cat > {file_path}"),//write smth (cat) inside a file ({})
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(BackendError::io("ERR_PLAYGROUND_CREATE_PROJECT_FILE"))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(contents.as_bytes())
+                .await
+                .map_err(BackendError::io("ERR_PLAYGROUND_WRITE_PROJECT_FILE"))?;
+            drop(stdin);//close the pipe so 'cat' can finish
+        }
+
+        let status = child.wait()
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_WAIT_CREATE_PROJECT_FILE"))?;
+
+        if !status.success() {
+            return Err(BackendError::Command {
+                context: "ERR_PLAYGROUND_WAIT_CREATE_PROJECT_FILE",
+                detail: format!("'cat' exited with {status}"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Copies `file` from the shared VOLUME to the RUNNER and executes it
+    /// with `args`, within `limits`.
+    ///
+    /// `args` are used in a shell script: they must not need quoting.
+    async fn run_in_runner(&self, id: Uuid, file: &str, args: &[&str], limits: &ResourceLimits) -> Result<Child, BackendError> {
+        //copy from VOLUME to RUNNER
+        self.exec(
+            &self.runner,
+            &format!("cp ../shared_folder/{file} ./{file}"),
+            "ERR_PLAYGROUND_CP_EXE"
+        ).await?;
+
+        //execute .exe as the uid of the session, dropped to before the limits are set
+        let script = format!(
+            "exec setpriv {} prlimit {} ./{file} {}",
+            setpriv_args(self.session_uid(id)),
+            limits.prlimit_args().join(" "),
+            args.join(" ")
+        );
+
+        Command::new("docker")
+            .args(["exec", "-i", "-e", "RUSTC_BOOTSTRAP=1", &self.runner, "sh", "-c", &script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(BackendError::io("ERR_PLAYGROUND_RUN_LAUNCH_EXEC"))
+    }
+
+    /// `cargo +<channel> <subcommand> --message-format=json <args>` on the
+    /// crate of session `id`, inside the COMPILER container, with the `env`
+    /// variables (`VAR=value`) set.
+    ///
+    /// Killed in the container after [`BUILD_TIMEOUT`], since dropping the
+    /// build only stops `docker exec`.
+    fn cargo_json(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        env: &[&str],
+        subcommand: &str,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    ) -> Command {
+        let mut command = Command::new("docker");
+        command.args(["exec", "-i", "-w", &session_dir(id), "-e", &format!("CARGO_TARGET_DIR={}", target_dir(id))]);
+        for var in env {
+            command.args(["-e", var]);
+        }
+        command
+            .args([&self.builder, "timeout", "-s", "KILL", &BUILD_TIMEOUT.as_secs().to_string()])
+            .args(["cargo", &format!("+{}", toolchain.channel), subcommand, "--message-format=json"])
+            .args(args);
+
+        command
+    }
+
+    async fn rm_file(&self, container: &str, file_path: &str) -> Result<(), BackendError> {
+        //WARNING: may not clear everything on container; be sure to run a deep cleaning script every run
+        let status = Command::new("docker")
+            .args(["exec", container, "rm", "-f", file_path])
+            .status()
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_RM_FILE"))?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(BackendError::Command {
+                context: "ERR_PLAYGROUND_RM_FILE",
+                detail: format!("failed to remove file '{container}:{file_path}'"),
+            }),
+        }
+    }
+}
+
+/// `setpriv` options switching to `uid`, with no supplementary groups.
+fn setpriv_args(uid: u32) -> String {
+    format!("--reuid={uid} --regid={uid} --clear-groups")
+}
+
+fn session_dir(id: Uuid) -> String {
+    format!("{SESSIONS_DIR}/{id}")
+}
+
+fn target_dir(id: Uuid) -> String {
+    format!("{SESSIONS_DIR}/{id}/target")
+}
+
+fn package_name(id: Uuid) -> String {
+    format!("session-{id}")
+}
+
+impl Default for DockerBackend {
+    fn default() -> Self {
+        DockerBackend::new(BUILDER_CONTAINER_NAME, RUNNER_CONTAINER_NAME)
+    }
+}
+
+#[async_trait]
+impl ExecutionBackend for DockerBackend {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    async fn check_available(&self) -> Result<(), BackendError> {
+        let output = Command::new("docker")
+            .args(["ps"])
+            .output()
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_DOCKER_PS"))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.contains(&self.builder) && stdout.contains(&self.runner) {
+            Ok(())
+        } else {
+            Err(BackendError::Unavailable(format!(
+                "Container '{}' OR '{}'", self.builder, self.runner
+            )))
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    async fn channels(&self) -> Result<Vec<Channel>, BackendError> {
+        self.channels.get(|| {
+            let mut command = Command::new("docker");
+            command.args(["exec", &self.builder, "rustup", "toolchain", "list"]);
+            command
+        }).await
+    }
+
+    async fn write_project(&self, id: Uuid, project: &Project, toolchain: &Toolchain) -> Result<(), BackendError> {
+        let dir = session_dir(id);
+
+        //manifest + lock file of the COMPILER crate, and a copy of its dependencies already built
+        //(a copy, not links: a session rebuilding them must not change the seed)
+        self.write_file(&self.builder, &format!("{dir}/Cargo.toml"), &manifest(&package_name(id), project, toolchain.edition)).await?;
+        self.exec(
+            &self.builder,
+            &format!(
+                "cp {COMPILE_DIR}/Cargo.lock {dir}/ && if [ ! -d {target} ]; then cp -a --reflink=auto {SEED_TARGET_DIR} {target}; fi",
+                target = target_dir(id)
+            ),
+            "ERR_PLAYGROUND_WRITE_MANIFEST"
+        ).await?;
+
+        //paths were validated: no quoting needed
+        for file in &project.files {
+            self.write_file(&self.builder, &format!("{dir}/{}", file.path), &file.contents).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn build(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        //cargo build --profile <..> => get exe + diagnostics as json (show compile problems then if ok execute)
+        let command = self.cargo_json(id, toolchain, &[], "build", profile_args(build));
+
+        let output = cargo_build(command, diagnostics).await?;
+
+        if output.status.success() {
+            //copy .exe from COMPILER to shared VOLUME (the package has no binary if it's only a library)
+            self.exec(
+                &self.builder,
+                &format!(
+                    "if [ -f {target}/{profile}/{package} ]; then cp {target}/{profile}/{package} ../shared_folder/{id}; fi",
+                    target = target_dir(id),
+                    profile = build.profile.as_str(),
+                    package = package_name(id)
+                ),
+                "ERR_PLAYGROUND_CP_EXE"
+            ).await?;
+        }
+
+        Ok(output)
+    }
+
+    async fn lint(&self, id: Uuid, toolchain: &Toolchain, diagnostics: DiagnosticSender) -> Result<BuildOutput, BackendError> {
+        cargo_build(self.cargo_json(id, toolchain, &[], "clippy", ["--all-targets"]), diagnostics).await
+    }
+
+    async fn build_tests(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        selection: &TestSelection,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        let args = std::iter::once("--no-run").chain(selection.cargo_args.iter().map(String::as_str));
+        let mut output = cargo_build(self.cargo_json(id, toolchain, &[], "test", args), diagnostics).await?;
+
+        //copy the test binaries from COMPILER to shared VOLUME, as <id>-test-<n>
+        for (n, binary) in output.tests.iter_mut().enumerate() {
+            let file = format!("{id}-test-{n}");
+            self.exec(
+                &self.builder,
+                &format!("cp {} ../shared_folder/{file}", binary.path),
+                "ERR_PLAYGROUND_CP_EXE"
+            ).await?;
+            binary.path = file;
+        }
+
+        Ok(output)
+    }
+
+    async fn inspect(
+        &self,
+        id: Uuid,
+        project: &Project,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        target: InspectTarget,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        let env: &[&str] = if inspect::is_unstable(target) { &["RUSTC_BOOTSTRAP=1"] } else { &[] };
+        let mut args = profile_args(build);
+        args.extend(inspect::rustc_args(target, project, &package_name(id)));
+
+        cargo_build(self.cargo_json(id, toolchain, env, "rustc", args), diagnostics).await
+    }
+
+    async fn run(&self, id: Uuid, _profile: BuildProfile, limits: &ResourceLimits) -> Result<Child, BackendError> {
+        //the build copied the binary of its profile to the shared VOLUME
+        self.run_in_runner(id, &id.to_string(), &[], limits).await
+    }
+
+    async fn run_test(&self, id: Uuid, binary: &TestBinary, selection: &TestSelection, limits: &ResourceLimits) -> Result<Child, BackendError> {
+        let args: Vec<&str> = TEST_HARNESS_ARGS.into_iter().chain(selection.harness_args.iter().map(String::as_str)).collect();
+        self.run_in_runner(id, &binary.path, &args, limits).await
+    }
+
+    async fn cargo(&self, id: Uuid, toolchain: &Toolchain, command: &CargoCommand) -> Result<Child, BackendError> {
+        //the arguments are positional parameters of the script ("$@"), never part of it
+        Command::new("docker")
+            .args([
+                "exec",
+                "-i",
+                "-w",
+                &session_dir(id),
+                "-e",
+                &format!("CARGO_TARGET_DIR={}", target_dir(id)),
+                &self.builder,
+                "sh",
+                "-c",
+                &format!("mkdir -p -m 700 {PID_DIR} && echo $$ > {PID_DIR}/{id}.pid && exec cargo \"$@\""),
+                "sh",
+                &format!("+{}", toolchain.channel),
+                command.subcommand.as_str(),
+            ])
+            .args(&command.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(BackendError::io("ERR_PLAYGROUND_CARGO_LAUNCH"))
+    }
+
+    async fn format(&self, source: &str, edition: Edition) -> Result<FormatOutput, BackendError> {
+        //no files involved: the source goes through stdin/stdout
+        let mut command = Command::new("docker");
+        command.args(["exec", "-i", &self.builder, "rustfmt", "--edition", edition.as_str()]);
+
+        rustfmt(command, source).await
+    }
+
+    async fn kill(&self, id: Uuid, child: &mut Child) -> Result<(), BackendError> {
+        //killing 'docker exec' alone would leave the program running in the container
+        let killed = self.kill_session_processes(id).await;
+
+        //in the COMPILER: the cargo command, by the pid it saved
+        let script = format!(
+            "if [ -f {PID_DIR}/{id}.pid ]; then pid=$(cat {PID_DIR}/{id}.pid) && (kill -KILL -- -$pid 2>/dev/null || kill -KILL $pid); fi"
+        );
+        let killed = killed.and(self.exec(&self.builder, &script, "ERR_PLAYGROUND_KILL_EXEC").await);
+
+        let _ = child.kill().await;
+        killed
+    }
+
+    fn termination_reason(&self, status: ExitStatus) -> TerminationReason {
+        //'docker exec' reports a program killed by a signal as 128 + signal
+        match status.code() {
+            Some(code) if code > 128 => signal_reason(code - 128),
+            Some(code) => TerminationReason::Exited { code },
+            None => TerminationReason::Exited { code: -1 },
+        }
+    }
+
+    async fn clean(&self, id: Uuid) -> Result<(), BackendError> {
+        //remove crate + its target dir from COMPILER
+        self.exec(
+            &self.builder,
+            &format!("rm -rf {dir} {PID_DIR}/{id}.pid", dir = session_dir(id)),
+            "ERR_PLAYGROUND_RM_FILE"
+        ).await?;
+
+        //rm exe (and test binaries) from RUNNER and VOLUME
+        self.rm_file(&self.runner, &format!("{id}")).await?;
+        self.rm_file(&self.runner, &format!("../shared_folder/{id}")).await?;
+        self.exec(
+            &self.runner,
+            &format!("rm -f ./{id}-test-* ../shared_folder/{id}-test-*"),
+            "ERR_PLAYGROUND_RM_FILE"
+        ).await?;
+
+        //nothing of the session may outlive it, its uid goes to the next one
+        self.kill_session_processes(id).await?;
+        self.uids.lock().unwrap().remove(&id);
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use playground_protocol::{InspectTarget, Project};
+
+/// Arguments of `cargo rustc` (besides the profile) making rustc print
+/// `target` on stdout instead of building the crate.
+///
+/// The extra rustc flags apply to a single target: the binary, `bin`, if the
+/// project has one, else the library.
+pub fn rustc_args(target: InspectTarget, project: &Project, bin: &str) -> Vec<String> {
+    let mut args = match project.has_binary() {
+        true => vec!["--bin".to_string(), bin.to_string()],
+        false => vec!["--lib".to_string()],
+    };
+
+    args.push("--".to_string());
+    let flags: &[&str] = match target {
+        //without debug info: no .loc/.cfi noise
+        InspectTarget::Asm => &["--emit=asm=-", "-Cdebuginfo=0"],
+        InspectTarget::LlvmIr => &["--emit=llvm-ir=-", "-Cdebuginfo=0"],
+        InspectTarget::Mir => &["--emit=mir=-"],
+        InspectTarget::Hir => &["-Zunpretty=hir"],
+        InspectTarget::Expanded => &["-Zunpretty=expanded"],
+    };
+    args.extend(flags.iter().map(|flag| flag.to_string()));
+    args
+}
+
+/// Whether rustc needs `RUSTC_BOOTSTRAP=1` to print `target` on stable.
+pub fn is_unstable(target: InspectTarget) -> bool {
+    matches!(target, InspectTarget::Hir | InspectTarget::Expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::ProjectFile;
+
+    use super::*;
+
+    #[test]
+    fn flags_apply_to_one_target() {
+        let args = rustc_args(InspectTarget::Asm, &Project::single("fn main() {}"), "playground");
+        assert_eq!(args, ["--bin", "playground", "--", "--emit=asm=-", "-Cdebuginfo=0"]);
+
+        let library = Project { files: vec![ProjectFile::new("src/lib.rs", "pub fn f() {}")], dependencies: Vec::new() };
+        assert_eq!(rustc_args(InspectTarget::Expanded, &library, "playground"), ["--lib", "--", "-Zunpretty=expanded"]);
+        assert!(is_unstable(InspectTarget::Expanded) && !is_unstable(InspectTarget::Mir));
+    }
+}
@@ -0,0 +1,127 @@
+use playground_protocol::{CargoCommand, TestResult, TestStatus};
+use serde::Deserialize;
+
+/// Arguments given to every test binary: one JSON event per line.
+///
+/// The JSON format is unstable: the binaries run with `RUSTC_BOOTSTRAP=1`.
+pub const TEST_HARNESS_ARGS: [&str; 7] = [
+    "-Z",
+    "unstable-options",
+    "--format",
+    "json",
+    "--report-time",
+    "--show-output",
+    "--test-threads=1",
+];
+
+/// Which tests to run: the targets cargo builds and the filters and flags
+/// given to every test binary, on top of [`TEST_HARNESS_ARGS`]. Every test
+/// by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestSelection {
+    pub cargo_args: Vec<String>,
+    pub harness_args: Vec<String>,
+}
+
+impl TestSelection {
+    /// The tests of a `cargo test` typed by the user.
+    ///
+    /// `command` must be valid (see [`CargoCommand::validate`]): its
+    /// arguments need no quoting.
+    pub fn from_command(command: &CargoCommand) -> Self {
+        let (cargo_args, mut harness_args) = command.test_args();
+        harness_args.retain(|arg| !TEST_HARNESS_ARGS.contains(&arg.as_str()));//given once only
+
+        TestSelection { cargo_args, harness_args }
+    }
+}
+
+/// A test binary built by [`super::ExecutionBackend::build_tests`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestBinary {
+    /// File of the test target, e.g. `src/lib.rs`.
+    pub suite: String,
+    /// Where the backend finds the binary.
+    pub path: String,
+}
+
+/// What a line printed by a test binary is.
+#[derive(Debug, PartialEq, Eq)]
+pub enum HarnessLine {
+    /// A test (named here) started running.
+    Started(String),
+    /// A test is over.
+    Finished(TestResult),
+    /// Any other event of the harness.
+    Event,
+    /// Not an event: printed by the tests without being captured.
+    Text,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LibtestEvent {
+    Test {
+        name: String,
+        event: String,
+        exec_time: Option<f64>,
+        stdout: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+pub fn parse_line(line: &str, suite: &str) -> HarnessLine {
+    let Ok(event) = serde_json::from_str::<LibtestEvent>(line) else {
+        return HarnessLine::Text;
+    };
+
+    let LibtestEvent::Test { name, event, exec_time, stdout } = event else {
+        return HarnessLine::Event;
+    };
+    let status = match event.as_str() {
+        "ok" => TestStatus::Passed,
+        "failed" => TestStatus::Failed,
+        "ignored" => TestStatus::Ignored,
+        "started" => return HarnessLine::Started(name),
+        _ => return HarnessLine::Event,//timeout (still running)
+    };
+
+    HarnessLine::Finished(TestResult {
+        name,
+        suite: suite.to_string(),
+        status,
+        duration_micros: exec_time.map(|secs| (secs * 1_000_000.0).round() as u64),
+        output: stdout.unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_harness_events() {
+        let line = r#"{ "type": "test", "name": "tests::adds", "event": "ok", "exec_time": 0.0012, "stdout": "hello\n" }"#;
+        assert_eq!(parse_line(line, "src/lib.rs"), HarnessLine::Finished(TestResult {
+            name: "tests::adds".to_string(),
+            suite: "src/lib.rs".to_string(),
+            status: TestStatus::Passed,
+            duration_micros: Some(1200),
+            output: "hello\n".to_string(),
+        }));
+
+        let line = r#"{ "type": "test", "event": "ignored", "name": "tests::slow" }"#;
+        assert!(matches!(
+            parse_line(line, "src/lib.rs"),
+            HarnessLine::Finished(TestResult { status: TestStatus::Ignored, duration_micros: None, .. })
+        ));
+
+        assert_eq!(
+            parse_line(r#"{ "type": "test", "event": "started", "name": "tests::adds" }"#, "src/lib.rs"),
+            HarnessLine::Started("tests::adds".to_string())
+        );
+        assert_eq!(parse_line(r#"{ "type": "suite", "event": "started", "test_count": 2 }"#, "src/lib.rs"), HarnessLine::Event);
+        assert_eq!(parse_line("printed from a thread", "src/lib.rs"), HarnessLine::Text);
+    }
+}
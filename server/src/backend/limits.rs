@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use playground_protocol::RunLimits;
+
+/// Longest a build (or lint, or inspection) can take: the code compiled can
+/// keep rustc busy too, e.g. with const evaluation.
+pub const BUILD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Limits applied to a single run of a client's program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub wall_time: Duration,
+    pub cpu_time_secs: u64,
+    pub memory_bytes: u64,
+    pub max_processes: u64,
+    pub max_output_bytes: u64,
+}
+
+/// Used when the client doesn't ask for anything specific.
+pub const DEFAULT_LIMITS: ResourceLimits = ResourceLimits {
+    wall_time: Duration::from_secs(10),
+    cpu_time_secs: 5,
+    memory_bytes: 256 * 1024 * 1024,
+    max_processes: 32,
+    max_output_bytes: 64 * 1024,
+};
+
+/// Upper bound of what a client can ask for.
+pub const MAX_LIMITS: ResourceLimits = ResourceLimits {
+    wall_time: Duration::from_secs(120),
+    cpu_time_secs: 60,
+    memory_bytes: 1024 * 1024 * 1024,
+    max_processes: 128,
+    max_output_bytes: 1024 * 1024,
+};
+
+impl ResourceLimits {
+    /// Applies the client's request on top of `defaults`, clamped to `max`.
+    pub fn resolve(requested: &RunLimits, defaults: &ResourceLimits, max: &ResourceLimits) -> Self {
+        let pick = |requested: Option<u64>, default: u64, max: u64| requested.unwrap_or(default).clamp(1, max);
+
+        ResourceLimits {
+            wall_time: Duration::from_secs(pick(
+                requested.wall_time_secs,
+                defaults.wall_time.as_secs(),
+                max.wall_time.as_secs(),
+            )),
+            cpu_time_secs: pick(requested.cpu_time_secs, defaults.cpu_time_secs, max.cpu_time_secs),
+            memory_bytes: pick(
+                requested.memory_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+                defaults.memory_bytes,
+                max.memory_bytes,
+            ),
+            max_processes: pick(requested.max_processes, defaults.max_processes, max.max_processes),
+            max_output_bytes: pick(requested.max_output_bytes, defaults.max_output_bytes, max.max_output_bytes),
+        }
+    }
+
+    /// `prlimit` options enforcing the CPU, memory and process limits.
+    ///
+    /// The hard CPU limit is one second above the soft one so the program
+    /// gets a SIGXCPU (reported as [`playground_protocol::TerminationReason::CpuTimeExceeded`])
+    /// before the final SIGKILL.
+    pub fn prlimit_args(&self) -> Vec<String> {
+        vec![
+            format!("--cpu={}:{}", self.cpu_time_secs, self.cpu_time_secs + 1),
+            format!("--as={}", self.memory_bytes),
+            format!("--nproc={}", self.max_processes),
+        ]
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        DEFAULT_LIMITS
+    }
+}
@@ -0,0 +1,420 @@
+use std::{path::PathBuf, process::Stdio};
+
+use async_trait::async_trait;
+use playground_protocol::{BuildOptions, BuildProfile, CargoCommand, Channel, Edition, InspectTarget, Project, Toolchain};
+use tokio::{fs, process::{Child, Command}};
+use uuid::Uuid;
+
+use super::{
+    cargo_messages::cargo_build, inspect, libtest::TEST_HARNESS_ARGS, manifest::{manifest, profile_args}, rustfmt::rustfmt,
+    rustup::ChannelCache, BackendError, BuildOutput, DiagnosticSender, ExecutionBackend, FormatOutput,
+    ResourceLimits, RetryPolicy, TestBinary, TestSelection,
+};
+
+const CRATE_NAME: &str = "playground";
+
+/// Builds and runs with the host's `cargo`, one crate (with its own target
+/// directory) per session in a temporary directory. No isolation at all: meant for development and tests
+/// on machines without Docker.
+///
+/// CPU time and memory are limited with `setrlimit`; the process limit is
+/// not applied since `RLIMIT_NPROC` counts every process of the host user.
+pub struct LocalBackend {
+    root: PathBuf,
+    retry: RetryPolicy,
+    channels: ChannelCache,
+}
+
+impl LocalBackend {
+    pub fn new(root: PathBuf) -> Self {
+        LocalBackend { root, retry: RetryPolicy::default(), channels: ChannelCache::default() }
+    }
+
+    pub fn with_retry(self, retry: RetryPolicy) -> Self {
+        LocalBackend { retry, ..self }
+    }
+
+    fn session_dir(&self, id: Uuid) -> PathBuf {
+        self.root.join(id.to_string())
+    }
+}
+
+impl Default for LocalBackend {
+    fn default() -> Self {
+        LocalBackend::new(std::env::temp_dir().join("rust_playground"))
+    }
+}
+
+#[async_trait]
+impl ExecutionBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn check_available(&self) -> Result<(), BackendError> {
+        match Command::new("cargo").arg("--version").output().await {
+            Ok(output) if output.status.success() => Ok(()),
+            _ => Err(BackendError::Unavailable("Local 'cargo'".to_string())),
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    async fn channels(&self) -> Result<Vec<Channel>, BackendError> {
+        self.channels.get(|| {
+            let mut command = Command::new("rustup");
+            command.args(["toolchain", "list"]);
+            command
+        }).await
+    }
+
+    async fn write_project(&self, id: Uuid, project: &Project, toolchain: &Toolchain) -> Result<(), BackendError> {
+        let dir = self.session_dir(id);
+        fs::create_dir_all(&dir)
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_CREATE_SESSION_DIR"))?;
+
+        fs::write(dir.join("Cargo.toml"), manifest(CRATE_NAME, project, toolchain.edition))
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_WRITE_MANIFEST"))?;
+
+        for file in &project.files {
+            let path = dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .await
+                    .map_err(BackendError::io("ERR_PLAYGROUND_CREATE_PROJECT_DIR"))?;
+            }
+            fs::write(path, &file.contents)
+                .await
+                .map_err(BackendError::io("ERR_PLAYGROUND_WRITE_PROJECT_FILE"))?;
+        }
+
+        Ok(())
+    }
+
+    async fn build(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        let mut command = cargo(toolchain);
+        command
+            .arg("build")
+            .args(profile_args(build))
+            .arg("--message-format=json")
+            .current_dir(self.session_dir(id));
+
+        cargo_build(command, diagnostics).await
+    }
+
+    async fn lint(&self, id: Uuid, toolchain: &Toolchain, diagnostics: DiagnosticSender) -> Result<BuildOutput, BackendError> {
+        let mut command = cargo(toolchain);
+        command
+            .args(["clippy", "--all-targets", "--message-format=json"])
+            .current_dir(self.session_dir(id));
+
+        cargo_build(command, diagnostics).await
+    }
+
+    async fn build_tests(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        selection: &TestSelection,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        let mut command = cargo(toolchain);
+        command
+            .args(["test", "--no-run", "--message-format=json"])
+            .args(&selection.cargo_args)
+            .current_dir(self.session_dir(id));
+
+        //the binaries stay where cargo put them
+        cargo_build(command, diagnostics).await
+    }
+
+    async fn inspect(
+        &self,
+        id: Uuid,
+        project: &Project,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        target: InspectTarget,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError> {
+        let mut command = cargo(toolchain);
+        command
+            .arg("rustc")
+            .args(profile_args(build))
+            .arg("--message-format=json")
+            .args(inspect::rustc_args(target, project, CRATE_NAME))
+            .current_dir(self.session_dir(id));
+        if inspect::is_unstable(target) {
+            command.env("RUSTC_BOOTSTRAP", "1");
+        }
+
+        cargo_build(command, diagnostics).await
+    }
+
+    async fn run(&self, id: Uuid, profile: BuildProfile, limits: &ResourceLimits) -> Result<Child, BackendError> {
+        let dir = self.session_dir(id);
+        let mut command = Command::new(dir.join("target").join(profile.as_str()).join(CRATE_NAME));
+        command.current_dir(dir);
+
+        spawn_limited(command, limits)
+    }
+
+    async fn run_test(&self, id: Uuid, binary: &TestBinary, selection: &TestSelection, limits: &ResourceLimits) -> Result<Child, BackendError> {
+        let mut command = Command::new(&binary.path);
+        command
+            .args(TEST_HARNESS_ARGS)
+            .args(&selection.harness_args)
+            .env("RUSTC_BOOTSTRAP", "1")
+            .current_dir(self.session_dir(id));
+
+        spawn_limited(command, limits)
+    }
+
+    async fn cargo(&self, id: Uuid, toolchain: &Toolchain, cargo_command: &CargoCommand) -> Result<Child, BackendError> {
+        let mut command = cargo(toolchain);
+        command
+            .arg(cargo_command.subcommand.as_str())
+            .args(&cargo_command.args)
+            .current_dir(self.session_dir(id))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        //own process group, so kill() reaches the test binaries too
+        #[cfg(unix)]
+        command.process_group(0);
+
+        command.spawn().map_err(BackendError::io("ERR_PLAYGROUND_CARGO_LAUNCH"))
+    }
+
+    async fn format(&self, source: &str, edition: Edition) -> Result<FormatOutput, BackendError> {
+        let mut command = Command::new("rustfmt");
+        command.args(["--edition", edition.as_str()]);
+
+        rustfmt(command, source).await
+    }
+
+    async fn kill(&self, _id: Uuid, child: &mut Child) -> Result<(), BackendError> {
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            // SAFETY: plain syscall; the group was created when spawning.
+            unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+        }
+
+        child.kill().await.map_err(BackendError::io("ERR_PLAYGROUND_KILL_EXEC"))
+    }
+
+    async fn clean(&self, id: Uuid) -> Result<(), BackendError> {
+        match fs::remove_dir_all(self.session_dir(id)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(BackendError::Io { context: "ERR_PLAYGROUND_RM_SESSION_DIR", source: e })
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `cargo +<channel>`, through the rustup proxy.
+fn cargo(toolchain: &Toolchain) -> Command {
+    let mut command = Command::new("cargo");
+    command.arg(format!("+{}", toolchain.channel));
+    command
+}
+
+/// Starts `command` with piped stdio, in its own process group and within
+/// the CPU time and memory `limits`.
+fn spawn_limited(mut command: Command, limits: &ResourceLimits) -> Result<Child, BackendError> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    #[cfg(unix)]
+    {
+        let cpu = rlimit(limits.cpu_time_secs, limits.cpu_time_secs + 1);
+        let memory = rlimit(limits.memory_bytes, limits.memory_bytes);
+
+        //own process group, so kill() reaches the children too
+        command.process_group(0);
+        // SAFETY: only async-signal-safe calls between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setrlimit(libc::RLIMIT_CPU, &cpu) != 0
+                    || libc::setrlimit(libc::RLIMIT_AS, &memory) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    command.spawn().map_err(BackendError::io("ERR_PLAYGROUND_RUN_LAUNCH_EXEC"))
+}
+
+#[cfg(unix)]
+fn rlimit(soft: u64, hard: u64) -> libc::rlimit {
+    libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t }
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::{ProjectFile, TerminationReason, TestStatus};
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::backend::libtest::{self, HarnessLine};
+
+    #[tokio::test]
+    async fn builds_and_runs_a_multi_file_project() {
+        let backend = LocalBackend::new(std::env::temp_dir().join("rust_playground_tests"));
+        let id = Uuid::new_v4();
+
+        let project = Project {
+            files: vec![
+                ProjectFile::new("src/main.rs", "fn main() { playground::ciao(); }"),
+                ProjectFile::new("src/lib.rs", "mod greet;\npub use greet::ciao;"),
+                ProjectFile::new("src/greet.rs", r#"pub fn ciao() { println!("ciao"); }"#),
+            ],
+            dependencies: Vec::new(),
+        };
+        backend.write_project(id, &project, &Toolchain::default()).await.unwrap();
+        let (diagnostics, _) = tokio::sync::mpsc::unbounded_channel();
+        let build = backend.build(id, &Toolchain::default(), &BuildOptions::default(), diagnostics).await.unwrap();
+        assert!(build.status.success(), "{}", build.log);
+
+        let mut child = backend.run(id, BuildProfile::Debug, &ResourceLimits::default()).await.unwrap();
+        let mut stdout = String::new();
+        child.stdout.take().unwrap().read_to_string(&mut stdout).await.unwrap();
+        assert!(child.wait().await.unwrap().success());
+        assert_eq!(stdout, "ciao\n");
+
+        backend.clean(id).await.unwrap();
+        assert!(!backend.session_dir(id).exists());
+    }
+
+    #[tokio::test]
+    async fn overflow_checks_follow_the_profile() {
+        let backend = LocalBackend::new(std::env::temp_dir().join("rust_playground_tests"));
+        let id = Uuid::new_v4();
+
+        let project = Project::single("fn main() { let x: u8 = std::hint::black_box(255); println!(\"{}\", x + 1); }");
+        backend.write_project(id, &project, &Toolchain::default()).await.unwrap();
+
+        for (profile, expected) in [(BuildProfile::Debug, None), (BuildProfile::Release, Some("0\n"))] {
+            let (diagnostics, _) = tokio::sync::mpsc::unbounded_channel();
+            let build = BuildOptions { profile, ..BuildOptions::default() };
+            let output = backend.build(id, &Toolchain::default(), &build, diagnostics).await.unwrap();
+            assert!(output.status.success(), "{}", output.log);
+
+            let mut child = backend.run(id, profile, &ResourceLimits::default()).await.unwrap();
+            let mut stdout = String::new();
+            child.stdout.take().unwrap().read_to_string(&mut stdout).await.unwrap();
+            let status = child.wait().await.unwrap();
+            match expected {
+                Some(expected) => assert_eq!((stdout.as_str(), status.success()), (expected, true)),
+                None => assert!(!status.success(), "{profile} build didn't panic on overflow"),
+            }
+        }
+
+        backend.clean(id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn builds_and_runs_tests() {
+        let backend = LocalBackend::new(std::env::temp_dir().join("rust_playground_tests"));
+        let id = Uuid::new_v4();
+
+        let project = Project {
+            files: vec![
+                ProjectFile::new("src/lib.rs", "pub fn two() -> i32 { 2 }\n#[test]\nfn works() { assert_eq!(two(), 2); }"),
+                ProjectFile::new("tests/it.rs", "#[test]\nfn fails() { assert_eq!(playground::two(), 3); }"),
+            ],
+            dependencies: Vec::new(),
+        };
+        backend.write_project(id, &project, &Toolchain::default()).await.unwrap();
+        let (diagnostics, _) = tokio::sync::mpsc::unbounded_channel();
+        let build = backend.build_tests(id, &Toolchain::default(), &TestSelection::default(), diagnostics).await.unwrap();
+        assert!(build.status.success(), "{}", build.log);
+
+        let mut suites: Vec<&str> = build.tests.iter().map(|binary| binary.suite.as_str()).collect();
+        suites.sort();
+        assert_eq!(suites, ["src/lib.rs", "tests/it.rs"]);
+
+        for binary in &build.tests {
+            let mut child = backend.run_test(id, binary, &TestSelection::default(), &ResourceLimits::default()).await.unwrap();
+            let mut stdout = String::new();
+            child.stdout.take().unwrap().read_to_string(&mut stdout).await.unwrap();
+            let status = child.wait().await.unwrap();
+
+            let results: Vec<TestStatus> = stdout
+                .lines()
+                .filter_map(|line| match libtest::parse_line(line, &binary.suite) {
+                    HarnessLine::Finished(result) => Some(result.status),
+                    _ => None,
+                })
+                .collect();
+            match binary.suite.as_str() {
+                "src/lib.rs" => assert_eq!((results, status.success()), (vec![TestStatus::Passed], true)),
+                _ => assert_eq!((results, status.success()), (vec![TestStatus::Failed], false)),
+            }
+        }
+
+        //a 'cargo test' typed by the user builds and runs only what it names
+        let command = CargoCommand::parse("cargo test --lib nothing_matches -- --exact").unwrap();
+        let selection = TestSelection::from_command(&command);
+        let (diagnostics, _) = tokio::sync::mpsc::unbounded_channel();
+        let build = backend.build_tests(id, &Toolchain::default(), &selection, diagnostics).await.unwrap();
+        assert_eq!(build.tests.iter().map(|binary| binary.suite.as_str()).collect::<Vec<_>>(), ["src/lib.rs"]);
+
+        let mut child = backend.run_test(id, &build.tests[0], &selection, &ResourceLimits::default()).await.unwrap();
+        let mut stdout = String::new();
+        child.stdout.take().unwrap().read_to_string(&mut stdout).await.unwrap();
+        assert!(child.wait().await.unwrap().success());
+        assert!(!stdout.lines().any(|line| matches!(libtest::parse_line(line, "src/lib.rs"), HarnessLine::Finished(_))));
+
+        backend.clean(id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn formats_a_source_file() {
+        let backend = LocalBackend::default();
+
+        let output = backend.format("fn main( ) {let x = 1;}", Edition::E2024).await.unwrap();
+        assert_eq!(output, FormatOutput::Formatted("fn main() {\n    let x = 1;\n}\n".to_string()));
+
+        let output = backend.format("fn main() {\nlet x = ;\n}", Edition::E2024).await.unwrap();
+        assert!(matches!(output, FormatOutput::Invalid { line: Some(2), column: Some(9), .. }), "{output:?}");
+    }
+
+    #[tokio::test]
+    async fn stops_a_program_over_its_cpu_time() {
+        let backend = LocalBackend::new(std::env::temp_dir().join("rust_playground_tests"));
+        let id = Uuid::new_v4();
+        let limits = ResourceLimits { cpu_time_secs: 1, ..ResourceLimits::default() };
+
+        let project = Project::single("fn main() { loop { std::hint::black_box(()); } }");
+        backend.write_project(id, &project, &Toolchain::default()).await.unwrap();
+        let (diagnostics, _) = tokio::sync::mpsc::unbounded_channel();
+        let release = BuildOptions { profile: BuildProfile::Release, ..BuildOptions::default() };
+        let build = backend.build(id, &Toolchain::default(), &release, diagnostics).await.unwrap();
+        assert!(build.status.success(), "{}", build.log);
+
+        let mut child = backend.run(id, BuildProfile::Release, &limits).await.unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(backend.termination_reason(status), TerminationReason::CpuTimeExceeded);
+
+        backend.clean(id).await.unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use playground_protocol::{BuildOptions, BuildProfile, Edition, Project, LIB_PATH};
+
+use super::crates::dependency_lines;
+
+/// Name of the library crate, whatever the package is called: lets
+/// `src/main.rs` (and the tests) write `use playground::...`.
+pub const LIB_NAME: &str = "playground";
+
+/// `Cargo.toml` of a project; its dependencies must be whitelisted (see
+/// [`super::crates::check_dependencies`]).
+pub fn manifest(package: &str, project: &Project, edition: Edition) -> String {
+    let mut manifest = format!(
+        "[package]\nname = \"{package}\"\nversion = \"0.1.0\"\nedition = \"{edition}\"\n\n"
+    );
+
+    if project.has_library() {
+        manifest.push_str(&format!("[lib]\nname = \"{LIB_NAME}\"\npath = \"{LIB_PATH}\"\n\n"));
+    }
+
+    manifest.push_str("[dependencies]\n");
+    manifest.push_str(&dependency_lines(&project.dependencies));
+    manifest
+}
+
+/// Arguments of `cargo build` for `build`: the profile, and its overrides
+/// as `--config` (the manifest is the same whatever the options).
+pub fn profile_args(build: &BuildOptions) -> Vec<String> {
+    let profile = match build.profile {
+        BuildProfile::Debug => "dev",
+        BuildProfile::Release => "release",
+    };
+
+    let mut args = vec!["--profile".to_string(), profile.to_string()];
+    if let Some(overflow_checks) = build.overflow_checks {
+        args.push("--config".to_string());
+        args.push(format!("profile.{profile}.overflow-checks={overflow_checks}"));
+    }
+    if let Some(opt_level) = build.opt_level {
+        args.push("--config".to_string());
+        args.push(format!("profile.{profile}.opt-level={}", opt_level.to_toml()));
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::OptLevel;
+
+    use super::*;
+
+    #[test]
+    fn profile_overrides_become_config_args() {
+        assert_eq!(profile_args(&BuildOptions::default()), ["--profile", "dev"]);
+
+        let build = BuildOptions { profile: BuildProfile::Release, overflow_checks: Some(true), opt_level: Some(OptLevel::Z) };
+        assert_eq!(profile_args(&build), [
+            "--profile",
+            "release",
+            "--config",
+            "profile.release.overflow-checks=true",
+            "--config",
+            "profile.release.opt-level=\"z\"",
+        ]);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{io, process::ExitStatus, time::Duration};
+
+use async_trait::async_trait;
+use playground_protocol::{BuildOptions, BuildProfile, CargoCommand, Channel, Diagnostic, Edition, InspectTarget, Project, TerminationReason, Toolchain};
+use tokio::{process::Child, sync::mpsc};
+use uuid::Uuid;
+
+pub mod cargo_messages;
+pub mod crates;
+pub mod docker;
+pub mod inspect;
+pub mod libtest;
+pub mod limits;
+pub mod local;
+pub mod manifest;
+pub mod rustfmt;
+pub mod rustup;
+
+pub use docker::DockerBackend;
+pub use libtest::{TestBinary, TestSelection};
+pub use limits::ResourceLimits;
+pub use local::LocalBackend;
+pub use rustfmt::FormatOutput;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("{0} currently unavailable")]
+    Unavailable(String),
+    #[error("{context}: {source}")]
+    Io { context: &'static str, source: io::Error },
+    #[error("{context}: {detail}")]
+    Command { context: &'static str, detail: String },
+}
+
+impl BackendError {
+    pub fn io(context: &'static str) -> impl FnOnce(io::Error) -> Self {
+        move |source| BackendError::Io { context, source }
+    }
+}
+
+/// How long a request waits for an unavailable backend (see
+/// [`ExecutionBackend::check_available`]) before giving up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub attempts: u8,
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { attempts: 10, delay: Duration::from_secs(3) }
+    }
+}
+
+/// Output of a finished build.
+pub struct BuildOutput {
+    pub status: ExitStatus,
+    /// What cargo printed on stderr (progress and failures); the diagnostics
+    /// are sent apart while building.
+    pub log: String,
+    /// Test binaries built, if the build included the tests.
+    pub tests: Vec<TestBinary>,
+    /// What rustc printed on stdout, e.g. the output of
+    /// [`ExecutionBackend::inspect`].
+    pub emitted: String,
+    /// `emitted` stopped at [`cargo_messages::MAX_EMITTED_LEN`] bytes.
+    pub emitted_truncated: bool,
+}
+
+/// Where a build sends the compiler diagnostics as they come.
+pub type DiagnosticSender = mpsc::UnboundedSender<Diagnostic>;
+
+/// Where the playground compiles and executes the client's code.
+///
+/// Every operation is scoped by the session id, so several sessions can use
+/// the same backend concurrently.
+#[async_trait]
+pub trait ExecutionBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Checks once whether the backend can accept work right now.
+    async fn check_available(&self) -> Result<(), BackendError>;
+
+    /// How often [`ExecutionBackend::check_available`] is retried.
+    fn retry_policy(&self) -> RetryPolicy;
+
+    /// Release channels of the toolchains installed, the ones a
+    /// [`Toolchain`] can ask for.
+    async fn channels(&self) -> Result<Vec<Channel>, BackendError>;
+
+    /// Lays out `project` as the crate of session `id` (for the edition of
+    /// `toolchain`), isolated from the crates of the other sessions.
+    ///
+    /// `project` must be valid (see [`Project::validate`]).
+    async fn write_project(&self, id: Uuid, project: &Project, toolchain: &Toolchain) -> Result<(), BackendError>;
+
+    /// Builds the crate of session `id` with `toolchain` and the profile of
+    /// `build`, sending each compiler diagnostic to `diagnostics`.
+    ///
+    /// Dropping the returned future aborts the build.
+    async fn build(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError>;
+
+    /// Builds the tests of the crate of session `id` picked by `selection`
+    /// (`cargo test --no-run`), like [`ExecutionBackend::build`].
+    async fn build_tests(
+        &self,
+        id: Uuid,
+        toolchain: &Toolchain,
+        selection: &TestSelection,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError>;
+
+    /// Starts a test binary returned by [`ExecutionBackend::build_tests`] with
+    /// [`libtest::TEST_HARNESS_ARGS`] and the harness arguments of
+    /// `selection`, like [`ExecutionBackend::run`].
+    async fn run_test(&self, id: Uuid, binary: &TestBinary, selection: &TestSelection, limits: &ResourceLimits) -> Result<Child, BackendError>;
+
+    /// Runs clippy on the crate of session `id`, sending each lint (and
+    /// compiler diagnostic) to `diagnostics`.
+    ///
+    /// Dropping the returned future aborts the linting.
+    async fn lint(&self, id: Uuid, toolchain: &Toolchain, diagnostics: DiagnosticSender) -> Result<BuildOutput, BackendError>;
+
+    /// Compiles the crate of session `id` (the one of `project`) like
+    /// [`ExecutionBackend::build`], but stops at `target`, which ends up in
+    /// [`BuildOutput::emitted`].
+    ///
+    /// Dropping the returned future aborts the compilation.
+    async fn inspect(
+        &self,
+        id: Uuid,
+        project: &Project,
+        toolchain: &Toolchain,
+        build: &BuildOptions,
+        target: InspectTarget,
+        diagnostics: DiagnosticSender,
+    ) -> Result<BuildOutput, BackendError>;
+
+    /// Starts the binary built with `profile`, with piped stdin, stdout and
+    /// stderr.
+    ///
+    /// The CPU time, memory and process limits are enforced by the backend;
+    /// wall time and output size are watched by the caller.
+    async fn run(&self, id: Uuid, profile: BuildProfile, limits: &ResourceLimits) -> Result<Child, BackendError>;
+
+    /// Starts `command` on the crate of session `id` with piped stdin, stdout
+    /// and stderr.
+    ///
+    /// `command` must be valid (see [`CargoCommand::validate`]); its
+    /// arguments are passed to cargo as they are, never through a shell.
+    /// It's never a `cargo run` or `cargo test`, which run the client's code:
+    /// they go through [`ExecutionBackend::run`] and [`ExecutionBackend::run_test`].
+    async fn cargo(&self, id: Uuid, toolchain: &Toolchain, command: &CargoCommand) -> Result<Child, BackendError>;
+
+    /// Formats a single source file with rustfmt.
+    ///
+    /// Dropping the returned future aborts the formatting.
+    async fn format(&self, source: &str, edition: Edition) -> Result<FormatOutput, BackendError>;
+
+    /// Kills the running program (or cargo command) of session `id`,
+    /// including anything it spawned.
+    async fn kill(&self, _id: Uuid, child: &mut Child) -> Result<(), BackendError> {
+        child.kill().await.map_err(BackendError::io("ERR_PLAYGROUND_KILL_EXEC"))
+    }
+
+    /// Tells how the program ended from the exit status of the process
+    /// returned by [`ExecutionBackend::run`].
+    fn termination_reason(&self, status: ExitStatus) -> TerminationReason {
+        #[cfg(unix)]
+        if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+            return signal_reason(signal);
+        }
+
+        TerminationReason::Exited { code: status.code().unwrap_or(-1) }
+    }
+
+    /// Removes every file left behind by session `id`.
+    async fn clean(&self, id: Uuid) -> Result<(), BackendError>;
+}
+
+/// Maps the signal that killed a program to the limit it most likely hit.
+pub fn signal_reason(signal: i32) -> TerminationReason {
+    match signal {
+        libc::SIGXCPU => TerminationReason::CpuTimeExceeded,
+        signal => TerminationReason::Signaled { signal },
+    }
+}
@@ -0,0 +1,103 @@
+use std::process::Stdio;
+
+use playground_protocol::ServerMessage;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use super::BackendError;
+
+/// What rustfmt made of a source file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormatOutput {
+    Formatted(String),
+    /// rustfmt could not parse the source.
+    Invalid { message: String, line: Option<u32>, column: Option<u32> },
+}
+
+impl From<FormatOutput> for ServerMessage {
+    fn from(output: FormatOutput) -> Self {
+        match output {
+            FormatOutput::Formatted(source) => ServerMessage::Formatted { source },
+            FormatOutput::Invalid { message, line, column } => ServerMessage::FormatError { message, line, column },
+        }
+    }
+}
+
+/// Pipes `source` through `command`, a `rustfmt` reading from stdin.
+pub async fn rustfmt(mut command: Command, source: &str) -> Result<FormatOutput, BackendError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(BackendError::io("ERR_PLAYGROUND_RUSTFMT_LAUNCH"))?;
+
+    //rustfmt reads the whole input before writing anything
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(source.as_bytes())
+            .await
+            .map_err(BackendError::io("ERR_PLAYGROUND_RUSTFMT_WRITE"))?;
+    }
+
+    let output = child.wait_with_output()
+        .await
+        .map_err(BackendError::io("ERR_PLAYGROUND_RUSTFMT_WAIT"))?;
+
+    if output.status.success() {
+        return Ok(FormatOutput::Formatted(String::from_utf8_lossy(&output.stdout).to_string()));
+    }
+
+    Ok(parse_error(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Message and location of the first error rustfmt printed, e.g.
+/// ```text
+/// error: expected expression, found `;`
+///  --> <stdin>:2:9
+/// ```
+fn parse_error(stderr: &str) -> FormatOutput {
+    let mut lines = stderr.lines().skip_while(|line| !line.starts_with("error"));
+
+    let Some(message) = lines.next() else {
+        return FormatOutput::Invalid { message: stderr.trim().to_string(), line: None, column: None };
+    };
+    //"error: msg" or "error[E0000]: msg"
+    let message = message.split_once(": ").map_or(message, |(_, message)| message).to_string();
+
+    let location = lines
+        .next()
+        .and_then(|line| line.trim_start().strip_prefix("--> "))
+        .and_then(|location| {
+            let mut parts = location.rsplitn(3, ':');
+            let column = parts.next()?.parse().ok()?;
+            let line = parts.next()?.parse().ok()?;
+            Some((line, column))
+        });
+
+    FormatOutput::Invalid {
+        message,
+        line: location.map(|(line, _)| line),
+        column: location.map(|(_, column)| column),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rustfmt_errors() {
+        let stderr = "error: expected expression, found `;`\n --> <stdin>:2:9\n  |\n2 | let x = ;\n  |         ^ expected expression\n\n";
+        assert_eq!(parse_error(stderr), FormatOutput::Invalid {
+            message: "expected expression, found `;`".to_string(),
+            line: Some(2),
+            column: Some(9),
+        });
+
+        assert_eq!(parse_error("rustfmt: unknown edition\n"), FormatOutput::Invalid {
+            message: "rustfmt: unknown edition".to_string(),
+            line: None,
+            column: None,
+        });
+    }
+}
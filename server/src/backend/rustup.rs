@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use playground_protocol::Channel;
+use tokio::{process::Command, sync::Mutex};
+
+use super::BackendError;
+
+/// How long the installed channels are trusted before asking rustup again.
+const CHANNELS_TTL: Duration = Duration::from_secs(60);
+
+/// The channels of [`installed_channels`], kept for [`CHANNELS_TTL`]: they're
+/// checked on every request, and listing them starts a process.
+#[derive(Default)]
+pub struct ChannelCache {
+    listed: Mutex<Option<(Instant, Vec<Channel>)>>,
+}
+
+impl ChannelCache {
+    /// The cached channels, or the ones listed by the `rustup toolchain list`
+    /// of `command` once they're too old.
+    pub async fn get(&self, command: impl FnOnce() -> Command) -> Result<Vec<Channel>, BackendError> {
+        let mut listed = self.listed.lock().await;//one listing at a time, the others wait for it
+        if let Some((at, channels)) = &*listed && at.elapsed() < CHANNELS_TTL {
+            return Ok(channels.clone());
+        }
+
+        let channels = installed_channels(command()).await?;
+        *listed = Some((Instant::now(), channels.clone()));
+        Ok(channels)
+    }
+}
+
+/// Runs `command`, a `rustup toolchain list`, and returns the release
+/// channels it lists.
+pub async fn installed_channels(mut command: Command) -> Result<Vec<Channel>, BackendError> {
+    let output = command
+        .output()
+        .await
+        .map_err(BackendError::io("ERR_PLAYGROUND_RUSTUP_LIST"))?;
+
+    if !output.status.success() {
+        return Err(BackendError::Command {
+            context: "ERR_PLAYGROUND_RUSTUP_LIST",
+            detail: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    Ok(parse_toolchain_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Channels among lines like `stable-x86_64-unknown-linux-gnu (default)`;
+/// dated (`nightly-2025-01-01-...`) and versioned (`1.87.0-...`) toolchains
+/// can't be selected with `+<channel>`, so they don't count.
+fn parse_toolchain_list(list: &str) -> Vec<Channel> {
+    Channel::ALL
+        .into_iter()
+        .filter(|channel| {
+            list.lines().any(|line| {
+                line.strip_prefix(channel.as_str())
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .is_some_and(|host| !host.starts_with(|c: char| c.is_ascii_digit()))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_installed_channels() {
+        let list = "stable-x86_64-unknown-linux-gnu (active, default)\n\
+                    nightly-2025-01-01-x86_64-unknown-linux-gnu\n\
+                    1.87.0-x86_64-unknown-linux-gnu\n";
+        assert_eq!(parse_toolchain_list(list), [Channel::Stable]);
+
+        let list = "stable-aarch64-unknown-linux-gnu\nbeta-aarch64-unknown-linux-gnu\nnightly-aarch64-unknown-linux-gnu\n";
+        assert_eq!(parse_toolchain_list(list), Channel::ALL);
+    }
+
+    #[tokio::test]
+    async fn channels_are_listed_once() {
+        let cache = ChannelCache::default();
+        let list = || {
+            let mut command = Command::new("echo");
+            command.arg("stable-x86_64-unknown-linux-gnu");
+            command
+        };
+
+        assert_eq!(cache.get(list).await.unwrap(), [Channel::Stable]);
+        assert_eq!(cache.get(|| panic!("listed again")).await.unwrap(), [Channel::Stable]);
+    }
+}
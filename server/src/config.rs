@@ -0,0 +1,450 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    backend::{
+        docker::{BUILDER_CONTAINER_NAME, RUNNER_CONTAINER_NAME},
+        RetryPolicy,
+    },
+    quotas::{ANONYMOUS_ROLE, DEFAULT_ROLE},
+};
+
+/// Read when no `--config` is given, if it exists.
+const DEFAULT_CONFIG_PATH: &str = "playground.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("ERR_PLAYGROUND_CONFIG_READ: {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("ERR_PLAYGROUND_CONFIG_PARSE: {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("invalid {key}: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+/// Command line of the server. Every flag can also be set with its
+/// environment variable, and overrides the configuration file.
+#[derive(Debug, Clone, Parser)]
+#[command(version, about = "Server of the Rust playground")]
+pub struct Args {
+    /// TOML configuration file [default: playground.toml, if it exists]
+    #[arg(long, env = "PLAYGROUND_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address the playground listens on
+    #[arg(long, env = "PLAYGROUND_ADDRESS")]
+    pub address: Option<SocketAddr>,
+    /// Address the playground listens on for WebSocket clients (the web app)
+    #[arg(long, env = "PLAYGROUND_WEBSOCKET_ADDRESS")]
+    pub websocket_address: Option<SocketAddr>,
+    /// Address of the Prometheus metrics endpoint
+    #[arg(long, env = "PLAYGROUND_METRICS_ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
+    /// Clients served at once
+    #[arg(long, env = "PLAYGROUND_MAX_CLIENTS")]
+    pub max_clients: Option<usize>,
+    /// Clients waiting for a free slot; the others are refused
+    #[arg(long, env = "PLAYGROUND_MAX_WAITING_CLIENTS")]
+    pub max_waiting_clients: Option<usize>,
+    /// Sessions (served or waiting) a user can have at once
+    #[arg(long, env = "PLAYGROUND_MAX_SESSIONS_PER_USER")]
+    pub max_sessions_per_user: Option<usize>,
+    /// Seconds the requests in progress get to finish when the server shuts down
+    #[arg(long, env = "PLAYGROUND_SHUTDOWN_GRACE_SECS")]
+    pub shutdown_grace_secs: Option<u64>,
+    /// PEM certificate chain of the server, to serve TLS
+    #[arg(long, env = "PLAYGROUND_TLS_CERT_FILE")]
+    pub tls_cert_file: Option<PathBuf>,
+    /// PEM private key of the certificate
+    #[arg(long, env = "PLAYGROUND_TLS_KEY_FILE")]
+    pub tls_key_file: Option<PathBuf>,
+    /// Where the code is built and run
+    #[arg(long, env = "PLAYGROUND_BACKEND", value_enum)]
+    pub backend: Option<BackendKind>,
+    /// Name of the COMPILER container (docker backend)
+    #[arg(long, env = "PLAYGROUND_BUILDER_CONTAINER")]
+    pub builder_container: Option<String>,
+    /// Name of the RUNNER container (docker backend)
+    #[arg(long, env = "PLAYGROUND_RUNNER_CONTAINER")]
+    pub runner_container: Option<String>,
+    /// Checks of an unavailable backend before a request is aborted
+    #[arg(long, env = "PLAYGROUND_BACKEND_RETRIES")]
+    pub backend_retries: Option<u8>,
+    /// Seconds between two checks of an unavailable backend
+    #[arg(long, env = "PLAYGROUND_BACKEND_RETRY_DELAY_SECS")]
+    pub backend_retry_delay_secs: Option<u64>,
+    /// File with the key the session tokens are signed with
+    #[arg(long, env = "PLAYGROUND_AUTH_KEY_FILE")]
+    pub auth_key_file: Option<PathBuf>,
+    /// Refuse the clients without a session token
+    #[arg(long, env = "PLAYGROUND_AUTH_REQUIRED")]
+    pub auth_required: Option<bool>,
+    /// Folder of the shared snippets
+    #[arg(long, env = "PLAYGROUND_SNIPPETS_DIR")]
+    pub snippets_dir: Option<PathBuf>,
+    /// Megabytes the shared snippets can take
+    #[arg(long, env = "PLAYGROUND_SNIPPETS_MAX_MB")]
+    pub snippets_max_mb: Option<u64>,
+    /// Filter of the logs, same syntax as RUST_LOG
+    #[arg(long, env = "PLAYGROUND_LOG")]
+    pub log: Option<String>,
+    /// Format of the logs
+    #[arg(long, env = "PLAYGROUND_LOG_FORMAT", value_enum)]
+    pub log_format: Option<LogFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    #[default]
+    Docker,
+    /// The host's cargo, without any isolation: only for development
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Settings of the server: the defaults, then the configuration file, then
+/// the environment and the command line (see [`Args`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub backend: BackendConfig,
+    pub auth: AuthConfig,
+    pub quotas: QuotasConfig,
+    pub snippets: SnippetsConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    /// Same protocol, one message per WebSocket message, for the browser.
+    pub websocket_address: SocketAddr,
+    /// Prometheus text format, on `GET /metrics`.
+    pub metrics_address: SocketAddr,
+    pub max_clients: usize,
+    pub max_waiting_clients: usize,
+    /// Served or waiting, per account (per address for anonymous clients).
+    pub max_sessions_per_user: usize,
+    /// Given to the requests in progress on shutdown, before they're stopped.
+    pub shutdown_grace_secs: u64,
+}
+
+/// TLS on the playground listener: plain TCP without a certificate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain, the server's certificate first.
+    pub cert_file: Option<PathBuf>,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1) of the certificate.
+    pub key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    pub builder_container: String,
+    pub runner_container: String,
+    pub retries: u8,
+    pub retry_delay_secs: u64,
+}
+
+/// Session tokens (see [`crate::auth::Authenticator`]): without a key every
+/// session is anonymous.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Shared with the account backend of the app, which signs the tokens.
+    pub key_file: Option<PathBuf>,
+    /// Refuse the clients without a token, instead of serving them anonymously.
+    pub required: bool,
+}
+
+/// Limits of the users, by role (see [`crate::quotas::Quotas`]): the role
+/// is named by the session token.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct QuotasConfig(pub BTreeMap<String, RoleQuota>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleQuota {
+    /// Runs (builds, tests, lints, ...) given back every minute.
+    pub runs_per_minute: u32,
+    /// Runs that can be made in a row.
+    pub burst: u32,
+    /// Seconds of compile and run time per day (UTC).
+    pub daily_compute_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnippetsConfig {
+    pub dir: PathBuf,
+    /// Once the snippets take that much, nothing more can be shared.
+    pub max_mb: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Same syntax as `RUST_LOG`.
+    pub filter: String,
+    pub format: LogFormat,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            websocket_address: SocketAddr::from(([127, 0, 0, 1], 8001)),
+            metrics_address: SocketAddr::from(([127, 0, 0, 1], 9100)),
+            max_clients: 10,
+            max_waiting_clients: 50,
+            max_sessions_per_user: 3,
+            shutdown_grace_secs: 30,
+        }
+    }
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        let retry = RetryPolicy::default();
+        BackendConfig {
+            kind: BackendKind::default(),
+            builder_container: BUILDER_CONTAINER_NAME.to_string(),
+            runner_container: RUNNER_CONTAINER_NAME.to_string(),
+            retries: retry.attempts,
+            retry_delay_secs: retry.delay.as_secs(),
+        }
+    }
+}
+
+impl Default for QuotasConfig {
+    fn default() -> Self {
+        let quota = |runs_per_minute, burst, daily_compute_secs| RoleQuota { runs_per_minute, burst, daily_compute_secs };
+
+        QuotasConfig(BTreeMap::from([
+            (ANONYMOUS_ROLE.to_string(), quota(4, 4, 15 * 60)),
+            (DEFAULT_ROLE.to_string(), quota(10, 5, 60 * 60)),
+            ("teacher".to_string(), quota(30, 10, 4 * 60 * 60)),
+        ]))
+    }
+}
+
+impl Default for SnippetsConfig {
+    fn default() -> Self {
+        SnippetsConfig { dir: PathBuf::from("snippets"), max_mb: 256 }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig { filter: "info".to_string(), format: LogFormat::default() }
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_grace_period(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+}
+
+impl BackendConfig {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy { attempts: self.retries, delay: Duration::from_secs(self.retry_delay_secs) }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file named by `args` (or [`DEFAULT_CONFIG_PATH`]),
+    /// applies the overrides of `args` and validates the result.
+    pub fn load(args: &Args) -> Result<Config, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Config::from_file(Path::new(DEFAULT_CONFIG_PATH))?,
+            None => Config::default(),
+        };
+
+        config.apply(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+    }
+
+    fn apply(&mut self, args: &Args) {
+        let Args {
+            config: _,
+            address,
+            websocket_address,
+            metrics_address,
+            max_clients,
+            max_waiting_clients,
+            max_sessions_per_user,
+            shutdown_grace_secs,
+            tls_cert_file,
+            tls_key_file,
+            backend,
+            builder_container,
+            runner_container,
+            backend_retries,
+            backend_retry_delay_secs,
+            auth_key_file,
+            auth_required,
+            snippets_dir,
+            snippets_max_mb,
+            log,
+            log_format,
+        } = args.clone();
+
+        override_with(&mut self.server.address, address);
+        override_with(&mut self.server.websocket_address, websocket_address);
+        override_with(&mut self.server.metrics_address, metrics_address);
+        override_with(&mut self.server.max_clients, max_clients);
+        override_with(&mut self.server.max_waiting_clients, max_waiting_clients);
+        override_with(&mut self.server.max_sessions_per_user, max_sessions_per_user);
+        override_with(&mut self.server.shutdown_grace_secs, shutdown_grace_secs);
+        override_with(&mut self.tls.cert_file, tls_cert_file.map(Some));
+        override_with(&mut self.tls.key_file, tls_key_file.map(Some));
+        override_with(&mut self.backend.kind, backend);
+        override_with(&mut self.backend.builder_container, builder_container);
+        override_with(&mut self.backend.runner_container, runner_container);
+        override_with(&mut self.backend.retries, backend_retries);
+        override_with(&mut self.backend.retry_delay_secs, backend_retry_delay_secs);
+        override_with(&mut self.auth.key_file, auth_key_file.map(Some));
+        override_with(&mut self.auth.required, auth_required);
+        override_with(&mut self.snippets.dir, snippets_dir);
+        override_with(&mut self.snippets.max_mb, snippets_max_mb);
+        override_with(&mut self.log.filter, log);
+        override_with(&mut self.log.format, log_format);
+    }
+
+    /// Checks the settings that can't work, before anything starts.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key, reason: &str| Err(ConfigError::Invalid { key, reason: reason.to_string() });
+
+        if self.server.max_clients == 0 {
+            return invalid("server.max_clients", "at least 1 client must be served at once");
+        }
+        if self.server.max_sessions_per_user == 0 {
+            return invalid("server.max_sessions_per_user", "every user must be allowed 1 session at least");
+        }
+        if self.server.websocket_address == self.server.address {
+            return invalid("server.websocket_address", "it is the address of the playground");
+        }
+        if [self.server.address, self.server.websocket_address].contains(&self.server.metrics_address) {
+            return invalid("server.metrics_address", "it is an address of the playground");
+        }
+        if self.tls.cert_file.is_some() != self.tls.key_file.is_some() {
+            return invalid("tls", "cert_file and key_file go together");
+        }
+        for (key, name) in [
+            ("backend.builder_container", &self.backend.builder_container),
+            ("backend.runner_container", &self.backend.runner_container),
+        ] {
+            if !is_container_name(name) {
+                return invalid(key, &format!("'{name}' is not a container name"));
+            }
+        }
+        if self.auth.required && self.auth.key_file.is_none() {
+            return invalid("auth.required", "the tokens can't be checked without auth.key_file");
+        }
+        for role in [ANONYMOUS_ROLE, DEFAULT_ROLE] {
+            if !self.quotas.0.contains_key(role) {
+                return invalid("quotas", &format!("the limits of the '{role}' role are missing"));
+            }
+        }
+        for (role, quota) in &self.quotas.0 {
+            if quota.runs_per_minute == 0 || quota.burst == 0 {
+                return invalid("quotas", &format!("'{role}' could never run anything"));
+            }
+        }
+        if self.snippets.dir.as_os_str().is_empty() {
+            return invalid("snippets.dir", "the folder can't be empty");
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log.filter) {
+            return invalid("log.filter", &format!("'{}': {e}", self.log.filter));
+        }
+
+        Ok(())
+    }
+}
+
+fn override_with<T>(setting: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *setting = value;
+    }
+}
+
+/// Like docker's: a letter or digit, then letters, digits, `_`, `.` or `-`.
+fn is_container_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_layered_and_checked() {
+        //the example file documents the defaults
+        let example: Config = toml::from_str(include_str!("../playground.example.toml")).unwrap();
+        assert_eq!(example, Config::default());
+
+        let mut config: Config = toml::from_str("[server]\nmax_clients = 30\n\n[backend]\nkind = \"local\"\n").unwrap();
+        assert_eq!(config.server.max_clients, 30);
+        assert_eq!(config.server.max_waiting_clients, 50);
+        assert_eq!(config.backend.kind, BackendKind::Local);
+
+        let args = Args::try_parse_from(["rust_playground", "--max-clients", "40", "--log-format", "json"]).unwrap();
+        config.apply(&args);
+        assert_eq!(config.server.max_clients, 40);
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.backend.kind, BackendKind::Local);
+        assert!(config.validate().is_ok());
+
+        config.backend.runner_container = "ruruny; rm -rf /".to_string();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { key: "backend.runner_container", .. })));
+        config.backend.runner_container = "ruruny".to_string();
+
+        config.apply(&Args::try_parse_from(["rust_playground", "--auth-required", "true"]).unwrap());
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { key: "auth.required", .. })));
+        config.apply(&Args::try_parse_from(["rust_playground", "--auth-key-file", "playground.key"]).unwrap());
+        assert!(config.validate().is_ok());
+
+        config.apply(&Args::try_parse_from(["rust_playground", "--tls-cert-file", "tls/cert.pem"]).unwrap());
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { key: "tls", .. })));
+
+        assert!(toml::from_str::<Config>("[server]\nmax_client = 30\n").is_err());
+
+        let config: Config = toml::from_str("[quotas.user]\nruns_per_minute = 1\nburst = 1\ndaily_compute_secs = 60\n").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { key: "quotas", .. })));
+    }
+}
@@ -1,15 +1,103 @@
-use std::io::{self};
+use std::{process::ExitCode, sync::Arc};
 
+use auth::Authenticator;
+use backend::{DockerBackend, ExecutionBackend, LocalBackend};
+use clap::Parser;
+use config::{Args, BackendConfig, BackendKind, Config, LogConfig, LogFormat};
+use quotas::Quotas;
+use snippets::SnippetStore;
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+
+mod auth;
+mod backend;
+mod config;
+mod metrics;
+mod quotas;
+mod snippets;
 mod tcp;
-mod models;
 
-fn main() {
-    match tcp::server::spawn_tcp_server() {
+/// Sets up the logs, filtered and printed as `config` says.
+///
+/// Every session, and every phase of a request in it, is a span: its events
+/// carry the session id, and its end is logged with its duration.
+fn init_logging(config: &LogConfig) {
+    let logs = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(&config.filter))//checked by Config::validate
+        .with_span_events(FmtSpan::CLOSE);
+
+    match config.format {
+        LogFormat::Text => logs.init(),
+        LogFormat::Json => logs.json().init(),
+    }
+}
+
+/// Resolves on Ctrl-C or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            },
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate => {},
+    }
+}
+
+/// The execution backend picked by `config`.
+fn select_backend(config: &BackendConfig) -> Arc<dyn ExecutionBackend> {
+    match config.kind {
+        BackendKind::Docker => Arc::new(
+            DockerBackend::new(&config.builder_container, &config.runner_container).with_retry(config.retry_policy())
+        ),
+        BackendKind::Local => Arc::new(LocalBackend::default().with_retry(config.retry_policy())),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let config = match Config::load(&Args::parse()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("'Server' exit status: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let auth = match Authenticator::new(&config.auth) {
+        Ok(auth) => Arc::new(auth),
+        Err(e) => {
+            eprintln!("'Server' exit status: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let tls = match tcp::tls::acceptor(&config.tls) {
+        Ok(tls) => tls,
+        Err(e) => {
+            eprintln!("'Server' exit status: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    init_logging(&config.log);
+
+    let backend = select_backend(&config.backend);
+    let snippets = Arc::new(SnippetStore::new(config.snippets.dir.clone(), config.snippets.max_mb * 1024 * 1024));
+    let quotas = Quotas::new(&config.quotas);
+
+    match tcp::server::spawn_tcp_server(&config.server, backend, snippets, auth, quotas, tls, shutdown_signal()).await {
         Ok(_) => {
-            println!("Server exited succesfully!");
+            tracing::info!("Server exited succesfully!");
+            ExitCode::SUCCESS
         }
         Err(e) => {
-            println!("'Server' exit status: {e}");
+            tracing::error!("'Server' exit status: {e}");
+            ExitCode::FAILURE
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,291 @@
+use std::{
+    fmt::Write,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::tcp::registry::SessionRegistry;
+
+/// Upper bounds (in seconds) of the buckets of the duration histograms.
+const DURATION_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+/// Longest request head accepted from a scraper.
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a request (or a whole session) went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Malformed or refused by the checks (project, toolchain, command, ...).
+    InvalidRequest,
+    /// Turned away: the sessions and the waiting queue were full.
+    Rejected,
+    /// The session token was missing or not valid.
+    Unauthenticated,
+    /// The user hit its limit of runs or of compile and run time.
+    RateLimited,
+    /// The backend stayed unavailable for every retry.
+    BackendUnavailable,
+    /// The client's code doesn't build.
+    BuildFailed,
+    /// The backend failed to write, build or run the project.
+    Backend,
+    /// A request took longer than the server allows.
+    Timeout,
+    /// The snippet store failed to save or load a snippet.
+    Snippets,
+    Internal,
+}
+
+impl Failure {
+    pub const ALL: [Failure; 10] = [
+        Failure::InvalidRequest,
+        Failure::Rejected,
+        Failure::Unauthenticated,
+        Failure::RateLimited,
+        Failure::BackendUnavailable,
+        Failure::BuildFailed,
+        Failure::Backend,
+        Failure::Timeout,
+        Failure::Snippets,
+        Failure::Internal,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Failure::InvalidRequest => "invalid_request",
+            Failure::Rejected => "rejected",
+            Failure::Unauthenticated => "unauthenticated",
+            Failure::RateLimited => "rate_limited",
+            Failure::BackendUnavailable => "backend_unavailable",
+            Failure::BuildFailed => "build_failed",
+            Failure::Backend => "backend",
+            Failure::Timeout => "timeout",
+            Failure::Snippets => "snippets",
+            Failure::Internal => "internal",
+        }
+    }
+}
+
+/// The metrics of this server, fed by the sessions and the pipeline.
+pub static METRICS: Metrics = Metrics::new();
+
+/// Counters and histograms of the server, served in the Prometheus text
+/// format by [`serve`].
+pub struct Metrics {
+    sessions: AtomicU64,
+    backend_retries: AtomicU64,
+    failures: [AtomicU64; Failure::ALL.len()],
+    compile_duration: Histogram,
+    run_duration: Histogram,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            sessions: AtomicU64::new(0),
+            backend_retries: AtomicU64::new(0),
+            failures: [const { AtomicU64::new(0) }; Failure::ALL.len()],
+            compile_duration: Histogram::new(),
+            run_duration: Histogram::new(),
+        }
+    }
+
+    pub fn session_started(&self) {
+        self.sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn backend_retried(&self) {
+        self.backend_retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn failed(&self, failure: Failure) {
+        self.failures[failure as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_compile(&self, duration: Duration) {
+        self.compile_duration.observe(duration);
+    }
+
+    pub fn observe_run(&self, duration: Duration) {
+        self.run_duration.observe(duration);
+    }
+
+    /// Every metric in the Prometheus text format, with the gauges of `registry`.
+    pub fn render(&self, registry: &SessionRegistry) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "playground_sessions_active", "gauge", "Clients being served.");
+        let _ = writeln!(out, "playground_sessions_active {}", registry.active_sessions());
+        header(&mut out, "playground_sessions_waiting", "gauge", "Clients waiting in the queue for a free slot.");
+        let _ = writeln!(out, "playground_sessions_waiting {}", registry.waiting_sessions());
+
+        header(&mut out, "playground_sessions_total", "counter", "Clients connected since the start.");
+        let _ = writeln!(out, "playground_sessions_total {}", self.sessions.load(Ordering::Relaxed));
+        header(&mut out, "playground_backend_retries_total", "counter", "Checks of an unavailable backend retried.");
+        let _ = writeln!(out, "playground_backend_retries_total {}", self.backend_retries.load(Ordering::Relaxed));
+
+        header(&mut out, "playground_failures_total", "counter", "Failed requests and sessions, by kind.");
+        for failure in Failure::ALL {
+            let count = self.failures[failure as usize].load(Ordering::Relaxed);
+            let _ = writeln!(out, "playground_failures_total{{kind=\"{}\"}} {count}", failure.as_str());
+        }
+
+        self.compile_duration.render(&mut out, "playground_compile_duration_seconds", "Time spent building projects.");
+        self.run_duration.render(&mut out, "playground_run_duration_seconds", "Time spent running programs, tests and commands.");
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Durations in the [`DURATION_BUCKETS`].
+struct Histogram {
+    state: Mutex<HistogramState>,
+}
+
+struct HistogramState {
+    buckets: [u64; DURATION_BUCKETS.len()],//cumulative, like the exposed ones
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Histogram {
+            state: Mutex::new(HistogramState { buckets: [0; DURATION_BUCKETS.len()], count: 0, sum: 0.0 }),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let mut state = self.state.lock().unwrap();
+
+        for (bucket, bound) in state.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        state.count += 1;
+        state.sum += secs;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let state = self.state.lock().unwrap();
+
+        header(out, name, "histogram", help);
+        for (count, bound) in state.buckets.iter().zip(DURATION_BUCKETS) {
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", state.count);
+        let _ = writeln!(out, "{name}_sum {}", state.sum);
+        let _ = writeln!(out, "{name}_count {}", state.count);
+    }
+}
+
+/// Answers `GET /metrics` on `addr` with [`METRICS`].
+///
+/// Returns only if `addr` can't be bound.
+pub async fn serve(addr: SocketAddr, registry: Arc<SessionRegistry>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("Metrics on http://{addr}/metrics");
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _addr)) => stream,
+            Err(e) => {
+                tracing::error!("Failed to accept a metrics scrape: {e}");
+                continue;
+            },
+        };
+        let registry = Arc::clone(&registry);
+
+        tokio::spawn(async move {
+            match timeout(SCRAPE_TIMEOUT, respond(stream, &METRICS, &registry)).await {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => tracing::warn!("ERR_PLAYGROUND_METRICS: {e}"),
+                Err(_elapsed) => tracing::warn!("ERR_PLAYGROUND_METRICS: scrape timed out"),
+            }
+        });
+    }
+}
+
+/// Reads the head of an HTTP request and answers with the metrics or a 404.
+async fn respond(mut stream: TcpStream, metrics: &Metrics, registry: &SessionRegistry) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+
+    while !head.windows(4).any(|end| end == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_LEN {
+            return Ok(());
+        }
+
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let (status, body) = match head.starts_with(b"GET /metrics ") {
+        true => ("200 OK", metrics.render(registry)),
+        false => ("404 Not Found", "Not found: try /metrics\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_are_rendered_for_prometheus() {
+        let metrics = Metrics::new();
+        let registry = SessionRegistry::new(1, 1, 1);
+
+        metrics.session_started();
+        metrics.failed(Failure::BuildFailed);
+        metrics.failed(Failure::BuildFailed);
+        metrics.observe_compile(Duration::from_millis(700));
+        metrics.observe_compile(Duration::from_secs(20));
+
+        let text = metrics.render(&registry);
+        for line in [
+            "# TYPE playground_sessions_active gauge",
+            "playground_sessions_active 0",
+            "playground_sessions_total 1",
+            "playground_failures_total{kind=\"build_failed\"} 2",
+            "playground_failures_total{kind=\"rejected\"} 0",
+            "# TYPE playground_compile_duration_seconds histogram",
+            "playground_compile_duration_seconds_bucket{le=\"0.5\"} 0",
+            "playground_compile_duration_seconds_bucket{le=\"1\"} 1",
+            "playground_compile_duration_seconds_bucket{le=\"30\"} 2",
+            "playground_compile_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "playground_compile_duration_seconds_sum 20.7",
+            "playground_compile_duration_seconds_count 2",
+            "playground_run_duration_seconds_count 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing '{line}' in:\n{text}");
+        }
+    }
+}
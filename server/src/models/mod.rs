@@ -1,2 +0,0 @@
-pub mod models;
-pub mod lib;
\ No newline at end of file
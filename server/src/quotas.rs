@@ -0,0 +1,214 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use playground_protocol::SessionClaims;
+
+use crate::config::{QuotasConfig, RoleQuota};
+
+/// Role of the clients without an account.
+pub const ANONYMOUS_ROLE: &str = "anonymous";
+/// Role of the accounts whose token names no role, or one without limits of its own.
+pub const DEFAULT_ROLE: &str = "user";
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A limit hit by a user, and how long until it can run again.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Limited {
+    #[error("at most {runs_per_minute} runs per minute are allowed")]
+    Runs { runs_per_minute: u32, retry_after: Duration },
+    #[error("the {daily_compute_secs}s of compile and run time of today are used up")]
+    DailyCompute { daily_compute_secs: u64, retry_after: Duration },
+}
+
+impl Limited {
+    /// Rounded up, so that the limit is over by then.
+    pub fn retry_after_secs(&self) -> u64 {
+        let (Limited::Runs { retry_after, .. } | Limited::DailyCompute { retry_after, .. }) = self;
+        retry_after.as_secs_f64().ceil() as u64
+    }
+}
+
+/// Who the limits are counted for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UserKey {
+    Account(String),
+    /// Anonymous clients, by address.
+    Address(IpAddr),
+}
+
+impl UserKey {
+    /// The account of `claims`, or `addr` for anonymous clients.
+    pub fn of(claims: Option<&SessionClaims>, addr: IpAddr) -> Self {
+        match claims {
+            Some(claims) => UserKey::Account(claims.login.clone()),
+            None => UserKey::Address(addr),
+        }
+    }
+}
+
+/// Limits on the runs (builds, tests, lints, ...) of every user, set by its
+/// role: a token bucket of runs, and a budget of compile and run time that
+/// starts over every day (UTC).
+pub struct Quotas {
+    roles: BTreeMap<String, RoleQuota>,
+    state: Mutex<QuotasState>,
+}
+
+struct QuotasState {
+    day: u64,//days since the epoch; the usage is of this day
+    users: HashMap<UserKey, Usage>,
+}
+
+struct Usage {
+    runs: f64,//left in the bucket
+    refilled_at: Duration,//since the epoch
+    compute: Duration,
+}
+
+/// A run allowed by [`Quotas::start_run`]: its time is charged to the user
+/// when dropped.
+pub struct RunCharge {
+    quotas: Arc<Quotas>,
+    user: UserKey,
+    started: Instant,
+}
+
+impl Quotas {
+    /// `config` was validated: it has the limits of [`ANONYMOUS_ROLE`] and [`DEFAULT_ROLE`].
+    pub fn new(config: &QuotasConfig) -> Arc<Self> {
+        Arc::new(Quotas {
+            roles: config.0.clone(),
+            state: Mutex::new(QuotasState { day: 0, users: HashMap::new() }),
+        })
+    }
+
+    /// Takes a run from the bucket of the user (the account of `claims`, or
+    /// `addr` for anonymous clients), if it has one left and time left today.
+    pub fn start_run(self: &Arc<Self>, claims: Option<&SessionClaims>, addr: IpAddr) -> Result<RunCharge, Limited> {
+        let user = UserKey::of(claims, addr);
+        self.take_run(&user, self.quota(claims), since_epoch())?;
+        Ok(RunCharge { quotas: Arc::clone(self), user, started: Instant::now() })
+    }
+
+    /// Limits of the role of `claims` (signed by the server of the app, from
+    /// its accounts): [`DEFAULT_ROLE`]'s if it has none or one without limits.
+    fn quota(&self, claims: Option<&SessionClaims>) -> &RoleQuota {
+        let role = match claims {
+            Some(claims) => claims.role.as_deref(),
+            None => Some(ANONYMOUS_ROLE),
+        };
+        role.and_then(|role| self.roles.get(role)).unwrap_or(&self.roles[DEFAULT_ROLE])
+    }
+
+    fn take_run(&self, user: &UserKey, quota: &RoleQuota, now: Duration) -> Result<(), Limited> {
+        let mut state = self.state.lock().unwrap();
+
+        //a new day: every budget starts over
+        let today = now.as_secs() / DAY.as_secs();
+        if state.day != today {
+            state.day = today;
+            state.users.clear();
+        }
+
+        let usage = state.users.entry(user.clone()).or_insert(Usage {
+            runs: f64::from(quota.burst),
+            refilled_at: now,
+            compute: Duration::ZERO,
+        });
+
+        let runs_per_sec = f64::from(quota.runs_per_minute) / 60.0;
+        usage.runs = (usage.runs + now.saturating_sub(usage.refilled_at).as_secs_f64() * runs_per_sec)
+            .min(f64::from(quota.burst));
+        usage.refilled_at = now;
+
+        if usage.compute >= Duration::from_secs(quota.daily_compute_secs) {
+            return Err(Limited::DailyCompute {
+                daily_compute_secs: quota.daily_compute_secs,
+                retry_after: Duration::from_secs((today + 1) * DAY.as_secs()) - now,
+            });
+        }
+        if usage.runs < 1.0 {
+            return Err(Limited::Runs {
+                runs_per_minute: quota.runs_per_minute,
+                retry_after: Duration::from_secs_f64((1.0 - usage.runs) / runs_per_sec),
+            });
+        }
+
+        usage.runs -= 1.0;
+        Ok(())
+    }
+
+    fn charge(&self, user: &UserKey, used: Duration) {
+        //nothing to charge if the day ended during the run
+        if let Some(usage) = self.state.lock().unwrap().users.get_mut(user) {
+            usage.compute += used;
+        }
+    }
+}
+
+impl Drop for RunCharge {
+    fn drop(&mut self) {
+        self.quotas.charge(&self.user, self.started.elapsed());
+    }
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_limited_per_user() {
+        let quota = RoleQuota { runs_per_minute: 6, burst: 2, daily_compute_secs: 60 };
+        let quotas = Quotas::new(&QuotasConfig(BTreeMap::from([(DEFAULT_ROLE.to_string(), quota.clone())])));
+        let mario = UserKey::Account("mario.rossi".to_string());
+        let luigi = UserKey::Account("luigi.verdi".to_string());
+        let noon = DAY * 20_000 + DAY / 2;
+
+        //the burst, then one run every 10 seconds
+        assert_eq!(quotas.take_run(&mario, &quota, noon), Ok(()));
+        assert_eq!(quotas.take_run(&mario, &quota, noon), Ok(()));
+        let limited = quotas.take_run(&mario, &quota, noon).unwrap_err();
+        assert!(matches!(limited, Limited::Runs { runs_per_minute: 6, .. }));
+        assert_eq!(limited.retry_after_secs(), 10);
+        assert_eq!(quotas.take_run(&luigi, &quota, noon), Ok(()));
+        assert_eq!(quotas.take_run(&mario, &quota, noon + Duration::from_secs(10)), Ok(()));
+
+        //then the time of the day is over, until midnight
+        quotas.charge(&mario, Duration::from_secs(60));
+        let limited = quotas.take_run(&mario, &quota, noon + Duration::from_secs(60)).unwrap_err();
+        assert!(matches!(limited, Limited::DailyCompute { daily_compute_secs: 60, .. }));
+        assert_eq!(limited.retry_after_secs(), DAY.as_secs() / 2 - 60);
+        assert_eq!(quotas.take_run(&mario, &quota, DAY * 20_001), Ok(()));
+    }
+
+    #[test]
+    fn roles_have_their_own_limits() {
+        let quota = |burst| RoleQuota { runs_per_minute: 1, burst, daily_compute_secs: 60 };
+        let quotas = Quotas::new(&QuotasConfig(BTreeMap::from([
+            (ANONYMOUS_ROLE.to_string(), quota(1)),
+            (DEFAULT_ROLE.to_string(), quota(2)),
+            ("teacher".to_string(), quota(3)),
+        ])));
+        let claims = |login: &str, role: Option<&str>| SessionClaims {
+            login: login.to_string(),
+            role: role.map(str::to_string),
+            expires_at: u64::MAX,
+        };
+        let runs = |claims: Option<&SessionClaims>, addr| {
+            (0..5).map_while(|_| quotas.start_run(claims, addr).ok()).count()
+        };
+
+        assert_eq!(runs(Some(&claims("prof.bianchi", Some("teacher"))), IpAddr::from([10, 0, 0, 1])), 3);
+        assert_eq!(runs(Some(&claims("mario.rossi", None)), IpAddr::from([10, 0, 0, 2])), 2);
+        assert_eq!(runs(Some(&claims("luigi.verdi", Some("janitor"))), IpAddr::from([10, 0, 0, 3])), 2);
+        assert_eq!(runs(None, IpAddr::from([10, 0, 0, 4])), 1);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{io, path::PathBuf};
+
+use playground_protocol::{is_snippet_id, Snippet, SNIPPET_ID_LEN};
+use tokio::{fs, sync::Mutex};
+use uuid::Uuid;
+
+const ID_ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnippetStoreError {
+    #[error("{context}: {source}")]
+    Io { context: &'static str, source: io::Error },
+    #[error("ERR_PLAYGROUND_SNIPPET_CORRUPTED: {0}")]
+    Corrupted(#[from] serde_json::Error),
+    #[error("ERR_PLAYGROUND_SNIPPETS_FULL: the snippets take {max_bytes} bytes at most")]
+    Full { max_bytes: u64 },
+}
+
+impl SnippetStoreError {
+    fn io(context: &'static str) -> impl FnOnce(io::Error) -> Self {
+        move |source| SnippetStoreError::Io { context, source }
+    }
+}
+
+/// Shared snippets, one JSON file per snippet (`<id>.json`) in a directory.
+///
+/// A snippet is never overwritten: its file appears complete, or not at all.
+/// The snippets take at most `max_bytes`: once full, nothing more is saved.
+pub struct SnippetStore {
+    dir: PathBuf,
+    max_bytes: u64,
+    used: Mutex<Option<u64>>,//bytes of the snippets, once the directory was read
+}
+
+impl SnippetStore {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        SnippetStore { dir, max_bytes, used: Mutex::new(None) }
+    }
+
+    /// Stores `snippet` under a new random id, and returns the id.
+    ///
+    /// `snippet` must be valid (see [`Snippet::validate`]).
+    pub async fn save(&self, snippet: &Snippet) -> Result<String, SnippetStoreError> {
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(SnippetStoreError::io("ERR_PLAYGROUND_SNIPPET_DIR"))?;
+
+        let json = serde_json::to_vec(snippet)?;
+        let mut used = self.used.lock().await;//held until the snippet is counted
+        let used = match *used {
+            Some(ref mut used) => used,
+            None => used.insert(self.stored_bytes().await?),
+        };
+        if *used + json.len() as u64 > self.max_bytes {
+            return Err(SnippetStoreError::Full { max_bytes: self.max_bytes });
+        }
+
+        //written aside, then linked under its id: the link fails if the id is taken
+        let temp = self.dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        fs::write(&temp, &json)
+            .await
+            .map_err(SnippetStoreError::io("ERR_PLAYGROUND_SNIPPET_WRITE"))?;
+
+        let saved = loop {
+            let id = new_id();
+            match fs::hard_link(&temp, self.path(&id)).await {
+                Ok(()) => break Ok(id),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => break Err(SnippetStoreError::Io { context: "ERR_PLAYGROUND_SNIPPET_WRITE", source: e }),
+            }
+        };
+
+        let _ = fs::remove_file(&temp).await;
+        if saved.is_ok() {
+            *used += json.len() as u64;
+        }
+        saved
+    }
+
+    /// Bytes of the snippets already in the directory.
+    async fn stored_bytes(&self) -> Result<u64, SnippetStoreError> {
+        let mut entries = fs::read_dir(&self.dir)
+            .await
+            .map_err(SnippetStoreError::io("ERR_PLAYGROUND_SNIPPET_DIR"))?;
+
+        let mut bytes = 0;
+        while let Some(entry) = entries.next_entry().await.map_err(SnippetStoreError::io("ERR_PLAYGROUND_SNIPPET_DIR"))? {
+            if entry.path().extension().is_some_and(|ext| ext == "json") {
+                bytes += entry.metadata().await.map_err(SnippetStoreError::io("ERR_PLAYGROUND_SNIPPET_DIR"))?.len();
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// The snippet stored as `id`, or `None` if there's none.
+    pub async fn load(&self, id: &str) -> Result<Option<Snippet>, SnippetStoreError> {
+        if !is_snippet_id(id) {
+            return Ok(None);
+        }
+
+        match fs::read(self.path(id)).await {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SnippetStoreError::Io { context: "ERR_PLAYGROUND_SNIPPET_READ", source: e }),
+        }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+/// [`SNIPPET_ID_LEN`] letters and digits out of the random bits of a v4
+/// uuid (about 60 bits): short, yet not guessable.
+fn new_id() -> String {
+    let mut bits = Uuid::new_v4().as_u128();
+    (0..SNIPPET_ID_LEN)
+        .map(|_| {
+            let c = ID_ALPHABET[(bits % ID_ALPHABET.len() as u128) as usize];
+            bits /= ID_ALPHABET.len() as u128;
+            c as char
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::{Project, Toolchain};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn snippets_are_stored_for_good() {
+        let store = SnippetStore::new(std::env::temp_dir().join("rust_playground_snippets_tests"), u64::MAX);
+        let snippet = Snippet {
+            project: Project::single("fn main() { println!(\"shared\"); }"),
+            toolchain: Toolchain::default(),
+            author: Some("ferris".to_string()),
+            ..Default::default()
+        };
+
+        let id = store.save(&snippet).await.unwrap();
+        assert!(is_snippet_id(&id), "{id}");
+        assert_eq!(store.load(&id).await.unwrap(), Some(snippet.clone()));
+
+        //sharing the same code again gives another snippet
+        assert_ne!(store.save(&snippet).await.unwrap(), id);
+
+        assert_eq!(store.load("0000000000").await.unwrap(), None);
+        assert_eq!(store.load("../../etc/passwd").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn full_stores_refuse_snippets() {
+        let dir = std::env::temp_dir().join(format!("rust_playground_snippets_{}", Uuid::new_v4()));
+        let snippet = Snippet { project: Project::single("fn main() {}"), ..Default::default() };
+        let len = serde_json::to_vec(&snippet).unwrap().len() as u64;
+
+        let store = SnippetStore::new(dir.clone(), 2 * len);
+        store.save(&snippet).await.unwrap();
+        store.save(&snippet).await.unwrap();
+        assert!(matches!(store.save(&snippet).await, Err(SnippetStoreError::Full { .. })));
+
+        //what is already stored counts after a restart too
+        let store = SnippetStore::new(dir.clone(), 3 * len);
+        store.save(&snippet).await.unwrap();
+        assert!(matches!(store.save(&snippet).await, Err(SnippetStoreError::Full { .. })));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,11 +1,10 @@
 use std::collections::VecDeque;
-use std::io::{self, ErrorKind};
+use std::io::ErrorKind;
 use std::net::TcpStream;
 use std::thread;
 use std::time::Duration;
 
-use crate::models::lib::*;
-use crate::models::models::JsonInfo;
+use playground_protocol::*;
 
 pub fn spawn_client() -> Result<(), Box<dyn std::error::Error>> {
     println!("Client started!");
@@ -31,11 +30,14 @@ pub fn rw_client(mut stream: TcpStream) {
     //init
     let mut shutdown = false;
 
-    let mut req: VecDeque<JsonInfo> = VecDeque::new();
-    req.push_back(JsonInfo::from(
-        "run&compile",
-        "fn main(){println!(\"CAGATI ADDOSSO . io!\")}"
-    ));
+    let mut req: VecDeque<ClientMessage> = VecDeque::new();
+    req.push_back(ClientMessage::hello());
+    req.push_back(ClientMessage::RunCompile {
+        project: Project::single("fn main(){println!(\"CAGATI ADDOSSO . io!\")}"),
+        toolchain: Toolchain::default(),
+        build: BuildOptions::default(),
+        limits: RunLimits::default(),
+    });
         // r#"use rand::Rng;
         // use std::cmp::Ordering;
         // use std::io;
@@ -71,23 +73,23 @@ pub fn rw_client(mut stream: TcpStream) {
         //         }
         //     }
         // }"#
-    // req.push_back(JsonInfo::from("dummy", ""));
+    // req.push_back(ClientMessage::Exit);
 
     //streaming loop
     while !shutdown {
         //READ ----------------------------------------
         loop {
-            match read_json_info(&mut stream) {
+            match read_message::<ServerMessage>(&mut stream) {
                 Ok(parsed_data) => {
                     println!("[server]: {parsed_data}");
 
-                    if parsed_data.header == "exit" {
+                    if let ServerMessage::Exit { .. } = parsed_data {
                         shutdown = true;
                         break;
                     }
                 },
                 Err(e) => {
-                    if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
+                    if let ProtocolError::Io(e) = &e {//get original error
                         match e.kind() {
                             ErrorKind::WouldBlock => {//non-block error
                                 break;
@@ -106,13 +108,13 @@ pub fn rw_client(mut stream: TcpStream) {
         }
     
         //WRITE ----------------------------------------
-        while let Some(r) = req.pop_front() {//foreach JsonInfo needed to be sent
-            match write_json_info(&mut stream, r) {
+        while let Some(r) = req.pop_front() {//foreach message needed to be sent
+            match write_message(&mut stream, &r) {
                 Ok(_ok) => {
                     // smth
                 },
                 Err(e) => {
-                    if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
+                    if let ProtocolError::Io(e) = &e {//get original error
                         match e.kind() {
                             ErrorKind::WouldBlock => {//non-block error
                                 // smth
@@ -0,0 +1,170 @@
+use std::{collections::VecDeque, io, net::SocketAddr, time::Duration};
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    select,
+    time::timeout,
+};
+
+use playground_protocol::*;
+
+use crate::tcp::shutdown::Shutdown;
+
+/// How long a message can wait for the client to read: a client that stops
+/// reading must not hold its session (and its program) forever.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the session loop can get out of the socket.
+pub enum Incoming {
+    Message(ClientMessage),
+    /// A frame arrived but its payload could not be parsed.
+    Corrupted(String),
+    /// The server began to shut down (returned once): the request in progress
+    /// can go on until the grace period is over.
+    ShuttingDown,
+    /// The peer went away (EOF or fatal io/framing error), or the server
+    /// shut down.
+    Closed,
+}
+
+/// Byte stream of a client: plain TCP, or TLS over it.
+pub trait ClientStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> ClientStream for S {}
+
+/// Protocol messages of a client, whatever carries them: length-prefixed
+/// frames over a [`ClientStream`] (a `Framed` [`ServerCodec`]), or WebSocket
+/// messages (see [`crate::tcp::websocket`]).
+///
+/// Like the codec, it yields a malformed message as an inner `Err`.
+pub trait Transport:
+    Stream<Item = Result<Result<ClientMessage, ProtocolError>, ProtocolError>>
+    + Sink<ServerMessage, Error = ProtocolError>
+    + Send
+    + Unpin
+{}
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<Result<ClientMessage, ProtocolError>, ProtocolError>>
+        + Sink<ServerMessage, Error = ProtocolError>
+        + Send
+        + Unpin
+{}
+
+/// Connection of a client, over any [`Transport`].
+pub struct Connection {
+    transport: Box<dyn Transport>,
+    peer: SocketAddr,
+    deferred: VecDeque<ClientMessage>,
+    shutdown: Shutdown,
+    shutdown_seen: bool,
+    send_timeout: Duration,
+    stalled: bool,//a send timed out: the connection is given up
+}
+
+impl Connection {
+    pub fn new(transport: Box<dyn Transport>, peer: SocketAddr, shutdown: Shutdown) -> Self {
+        Connection {
+            transport,
+            peer,
+            deferred: VecDeque::new(),
+            shutdown,
+            shutdown_seen: false,
+            send_timeout: SEND_TIMEOUT,
+            stalled: false,
+        }
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// Puts back a message received too early (e.g. while queued) so that the
+    /// next [`Connection::recv`] returns it again.
+    pub fn defer(&mut self, msg: ClientMessage) {
+        self.deferred.push_back(msg);
+    }
+
+    /// Waits for the next client message.
+    ///
+    /// Cancel safe: it can be used as a `tokio::select!` branch without
+    /// losing partially received frames.
+    pub async fn recv(&mut self) -> Incoming {
+        if self.stalled {
+            return Incoming::Closed;
+        }
+        if let Some(msg) = self.deferred.pop_front() {
+            return Incoming::Message(msg);
+        }
+
+        let frame = select! {
+            biased;
+            _ = self.shutdown.expired() => return Incoming::Closed,
+            _ = self.shutdown.started(), if !self.shutdown_seen => {
+                self.shutdown_seen = true;
+                return Incoming::ShuttingDown;
+            },
+            frame = self.transport.next() => frame,
+        };
+
+        match frame {
+            Some(Ok(Ok(msg))) => Incoming::Message(msg),
+            Some(Ok(Err(e))) => Incoming::Corrupted(e.to_string()),
+            //TLS peers often leave without saying goodbye (close_notify)
+            Some(Err(ProtocolError::Io(e))) if e.kind() == std::io::ErrorKind::UnexpectedEof => Incoming::Closed,
+            Some(Err(e)) => {
+                tracing::error!("Server error while listening: {e}");
+                Incoming::Closed
+            },
+            None => Incoming::Closed,
+        }
+    }
+
+    /// Sends `msg`, giving up on the client if it doesn't read it within
+    /// [`SEND_TIMEOUT`]: every later send fails, and [`Connection::recv`]
+    /// returns [`Incoming::Closed`].
+    pub async fn send(&mut self, msg: ServerMessage) -> Result<(), ProtocolError> {
+        if self.stalled {
+            return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+        }
+
+        match timeout(self.send_timeout, self.transport.send(msg)).await {
+            Ok(sent) => sent,
+            Err(_) => {
+                tracing::warn!("client stopped reading, closing the session");
+                self.stalled = true;
+                Err(io::Error::from(io::ErrorKind::TimedOut).into())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::duplex;
+    use tokio_util::codec::Framed;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn clients_that_stop_reading_are_closed() {
+        let (server, _client) = duplex(1024);
+        let mut conn = Connection::new(
+            Box::new(Framed::new(server, ServerCodec::new())),
+            "127.0.0.1:4242".parse().unwrap(),
+            Shutdown::new(Duration::ZERO),
+        );
+        conn.send_timeout = Duration::from_millis(50);
+
+        //the buffer of the client fills up, nobody reads it
+        let data = "x".repeat(4096);
+        assert!(conn.send(ServerMessage::Stdout { data: data.clone() }).await.is_err());
+        assert!(conn.send(ServerMessage::Stdout { data }).await.is_err());
+        assert!(matches!(conn.recv().await, Incoming::Closed));
+    }
+}
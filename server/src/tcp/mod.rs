@@ -1,2 +1,9 @@
 pub mod server;
-pub mod client;
\ No newline at end of file
+pub mod connection;
+pub mod pipeline;
+pub mod registry;
+pub mod shutdown;
+pub mod tls;
+pub mod websocket;
+#[allow(dead_code)]//manual test client, not wired in main
+pub mod client;
@@ -0,0 +1,877 @@
+use std::{future::Future, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Child,
+    select,
+    sync::mpsc,
+    time::{sleep, sleep_until, timeout, Instant},
+};
+use tracing::{debug, error, info, instrument, trace, warn};
+use uuid::Uuid;
+
+use playground_protocol::*;
+
+use crate::{
+    backend::{
+        crates,
+        libtest::{self, HarnessLine},
+        limits::{BUILD_TIMEOUT, DEFAULT_LIMITS, MAX_LIMITS},
+        BackendError, BuildOutput, ExecutionBackend, ResourceLimits, TestSelection,
+    },
+    metrics::{Failure, METRICS},
+    tcp::connection::{Connection, Incoming},
+};
+
+const OUTPUT_CHUNK_LEN: usize = 1024;
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most bytes of an [`ServerMessage::InspectOutput`]: even escaped, it fits in a frame.
+const INSPECT_CHUNK_LEN: usize = 16 * 1024;
+/// Most bytes of the log of a build sent back: even escaped, it fits in a frame.
+const MAX_BUILD_LOG_LEN: usize = 16 * 1024;
+/// Printed on stderr by the Rust runtime when an allocation fails.
+const OOM_MARKER: &str = "memory allocation of";
+
+pub type DynError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failures of a request that aren't errors of the backend.
+#[derive(Debug, thiserror::Error)]
+enum RequestError {
+    #[error("Request aborted due to exceed on time limit")]
+    Unavailable,
+    #[error("{what} failed with status: {code:?}\n{log:?}")]
+    BuildFailed { what: &'static str, code: Option<i32>, log: String },
+    #[error("Build stopped after {}s", BUILD_TIMEOUT.as_secs())]
+    BuildTimedOut,
+}
+
+/// How `err` is counted in the METRICS.
+fn failure_kind(err: &DynError) -> Failure {
+    match (err.downcast_ref::<RequestError>(), err.downcast_ref::<BackendError>()) {
+        (Some(RequestError::Unavailable), _) | (_, Some(BackendError::Unavailable(_))) => Failure::BackendUnavailable,
+        (Some(RequestError::BuildFailed { .. }), _) => Failure::BuildFailed,
+        (Some(RequestError::BuildTimedOut), _) => Failure::Timeout,
+        (_, Some(_)) => Failure::Backend,
+        (None, None) => Failure::Internal,
+    }
+}
+
+
+/// Handles a message received while a request is in progress.
+///
+/// Returns `true` if the client left (exit or disconnection).
+pub async fn client_left(conn: &mut Connection, incoming: Incoming) -> bool {
+    match incoming {
+        Incoming::Message(ClientMessage::Exit) | Incoming::Closed => true,
+        Incoming::Message(msg) => {
+            let _ = conn.send(ServerMessage::error(format!("Request ignored while busy: {msg}"))).await;
+            false
+        },
+        Incoming::Corrupted(reason) => {
+            let _ = conn.send(ServerMessage::RequestCorrupted { reason }).await;
+            false
+        },
+        Incoming::ShuttingDown => {
+            let grace_period_secs = conn.shutdown().grace_period().as_secs();
+            let _ = conn.send(ServerMessage::ShuttingDown { grace_period_secs }).await;
+            false
+        },
+    }
+}
+
+/// Builds `project` and runs its binary, then cleans the backend.
+///
+/// Returns `false` if the client left in the meantime.
+pub async fn run_compile(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: Project,
+    toolchain: Toolchain,
+    build: BuildOptions,
+    limits: RunLimits,
+    id: Uuid,
+) -> bool {
+    let mut connected = true;
+    let limits = ResourceLimits::resolve(&limits, &DEFAULT_LIMITS, &MAX_LIMITS);
+
+    match compile(conn, backend, &project, &toolchain, &build, id).await {
+        Ok(true) if !project.has_binary() => {
+            let _ = conn.send(ServerMessage::status(
+                format!("Nothing to run: the project has no {MAIN_PATH}")
+            )).await;
+        },
+        Ok(true) => {
+            match run(conn, backend, id, build.profile, &limits).await {
+                Ok(still_connected) => connected = still_connected,
+                Err(err) => {
+                    METRICS.failed(failure_kind(&err));
+                    warn!(error = %err, "run failed");
+                }
+            }
+        }
+        Ok(false) => connected = false,
+        Err(err) => {
+            METRICS.failed(failure_kind(&err));
+            warn!(error = %err, "compile failed");
+        }
+    }
+
+    finish(conn, backend, id, connected).await
+}
+
+/// Builds the tests of `project` picked by `selection` and runs them, then
+/// cleans the backend.
+///
+/// Returns `false` if the client left in the meantime.
+pub async fn run_tests(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: Project,
+    toolchain: Toolchain,
+    selection: TestSelection,
+    limits: RunLimits,
+    id: Uuid,
+) -> bool {
+    let limits = ResourceLimits::resolve(&limits, &DEFAULT_LIMITS, &MAX_LIMITS);
+    let connected = match test(conn, backend, &project, &toolchain, &selection, &limits, id).await {
+        Ok(still_connected) => still_connected,
+        Err(err) => {
+            METRICS.failed(failure_kind(&err));
+            warn!(error = %err, "tests failed");
+            true
+        }
+    };
+
+    finish(conn, backend, id, connected).await
+}
+
+/// Lints `project` with clippy, then cleans the backend.
+///
+/// Returns `false` if the client left in the meantime.
+pub async fn run_lint(conn: &mut Connection, backend: &dyn ExecutionBackend, project: Project, toolchain: Toolchain, id: Uuid) -> bool {
+    let connected = match lint(conn, backend, &project, &toolchain, id).await {
+        Ok(still_connected) => still_connected,
+        Err(err) => {
+            METRICS.failed(failure_kind(&err));
+            warn!(error = %err, "lint failed");
+            true
+        }
+    };
+
+    finish(conn, backend, id, connected).await
+}
+
+/// Compiles `project` up to `target` and sends it, then cleans the backend.
+///
+/// Returns `false` if the client left in the meantime.
+pub async fn run_inspect(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: Project,
+    target: InspectTarget,
+    toolchain: Toolchain,
+    build: BuildOptions,
+    id: Uuid,
+) -> bool {
+    let connected = match inspect(conn, backend, &project, target, &toolchain, &build, id).await {
+        Ok(still_connected) => still_connected,
+        Err(err) => {
+            METRICS.failed(failure_kind(&err));
+            warn!(error = %err, "inspect failed");
+            true
+        }
+    };
+
+    finish(conn, backend, id, connected).await
+}
+
+/// Runs a cargo command on `project`, then cleans the backend.
+///
+/// `cargo run` is the same as [`run_compile`], in debug unless it has
+/// `--release`, and `cargo test` the same as [`run_tests`]: the client's code
+/// only runs in the sandbox of the backend, within the limits.
+/// Returns `false` if the client left in the meantime.
+pub async fn run_command(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: Project,
+    command: CargoCommand,
+    toolchain: Toolchain,
+    limits: RunLimits,
+    id: Uuid,
+) -> bool {
+    if command.subcommand == CargoSubcommand::Run {
+        let profile = match command.args.iter().any(|arg| arg == "--release") {
+            true => BuildProfile::Release,
+            false => BuildProfile::Debug,
+        };
+        let build = BuildOptions { profile, ..BuildOptions::default() };
+        return run_compile(conn, backend, project, toolchain, build, limits, id).await;
+    }
+    if command.subcommand == CargoSubcommand::Test {
+        return run_tests(conn, backend, project, toolchain, TestSelection::from_command(&command), limits, id).await;
+    }
+
+    let limits = ResourceLimits::resolve(&limits, &DEFAULT_LIMITS, &MAX_LIMITS);
+    let connected = match cargo(conn, backend, &project, &command, &toolchain, &limits, id).await {
+        Ok(still_connected) => still_connected,
+        Err(err) => {
+            METRICS.failed(failure_kind(&err));
+            warn!(error = %err, "command failed");
+            true
+        }
+    };
+
+    finish(conn, backend, id, connected).await
+}
+
+/// Formats `source` and sends the client either the result or where
+/// rustfmt failed to parse it.
+#[instrument(skip_all)]
+pub async fn format(conn: &mut Connection, backend: &dyn ExecutionBackend, source: String, edition: Edition) {
+    let message = match timeout(FORMAT_TIMEOUT, backend.format(&source, edition)).await {
+        Ok(Ok(output)) => output.into(),
+        Ok(Err(_err)) => {
+            METRICS.failed(Failure::Backend);
+            error!(error = %_err, "format failed");
+            ServerMessage::error("Could not format the code, try again later")
+        },
+        Err(_elapsed) => {
+            METRICS.failed(Failure::Timeout);
+            ServerMessage::error(format!("Formatting took more than {}s", FORMAT_TIMEOUT.as_secs()))
+        },
+    };
+
+    let _ = conn.send(message).await;
+}
+
+/// Cleans the backend and tells the client the request is over.
+#[instrument(name = "clean", skip_all)]
+async fn finish(conn: &mut Connection, backend: &dyn ExecutionBackend, id: Uuid, connected: bool) -> bool {
+    match backend.clean(id).await {
+        Ok(_ok) => {
+            debug!("{} backend succesfully cleaned!", backend.name());
+        }
+        Err(_err) => {
+            error!(error = %_err, "clean failed");
+        }
+    }
+
+    // send exit
+    if connected {
+        let _o = conn.send(ServerMessage::exit("gracefully exit")).await;
+    }
+
+    connected
+}
+
+/// Waits until the backend is up (e.g. the containers are running).
+///
+/// Returns `false` if the client left while waiting.
+async fn wait_available(conn: &mut Connection, backend: &dyn ExecutionBackend) -> Result<bool, DynError> {
+    let retry = backend.retry_policy();
+    let mut retry_counter: u8 = 0;
+
+    loop {
+        match backend.check_available().await {
+            Ok(()) => return Ok(true),
+            Err(_err) => warn!(error = %_err, "backend unavailable"),
+        }
+
+        if retry_counter >= retry.attempts {
+            let _o = conn.send(
+                ServerMessage::error("Request aborted due to exceed on time limit")
+            ).await;
+            return Err(RequestError::Unavailable.into());
+        }
+
+        info!(retry = retry_counter + 1, "Retrying in {}s...", retry.delay.as_secs());
+        retry_counter += 1;
+        METRICS.backend_retried();
+
+        select! {
+            _ = sleep(retry.delay) => {},
+            incoming = conn.recv() => {
+                if client_left(conn, incoming).await {
+                    return Ok(false);
+                }
+            },
+        }
+    }
+}
+
+/// Builds the client's project.
+///
+/// Returns `false` if the client left before the build was over.
+#[instrument(skip_all)]
+pub async fn compile(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: &Project,
+    toolchain: &Toolchain,
+    build: &BuildOptions,
+    id: Uuid,
+) -> Result<bool, DynError> {
+    if !wait_available(conn, backend).await? {
+        return Ok(false);
+    }
+
+    let _ = conn.send(ServerMessage::status(format!(
+        "REQUEST STATUS ----------\nBuilding the file ({build}; {toolchain}). This may take a few time..."
+    ))).await;
+
+    backend.write_project(id, project, toolchain).await?;
+    debug!("Created the project files succesfully");
+
+    let (diagnostics_tx, diagnostics) = mpsc::unbounded_channel();
+    let Some(output) = forward_diagnostics(conn, project, backend.build(id, toolchain, build, diagnostics_tx), diagnostics, false).await? else {
+        return Ok(false);
+    };
+
+    debug!(log = %output.log, "build finished");
+
+    let _r = conn.send(compilation_result(&output.log)).await;
+
+    if !output.status.success() {
+        return Err(RequestError::BuildFailed { what: "Build", code: output.status.code(), log: output.log }.into());
+    }
+
+    Ok(true)
+}
+
+/// Builds the tests of `project` and runs every test binary, sending a
+/// [`ServerMessage::TestResult`] per test and then the totals.
+///
+/// The wall time limit applies to the whole run; a binary with failing tests
+/// doesn't stop the others, a limit does.
+/// Returns `false` if the client left in the meantime.
+#[instrument(skip_all)]
+async fn test(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: &Project,
+    toolchain: &Toolchain,
+    selection: &TestSelection,
+    limits: &ResourceLimits,
+    id: Uuid,
+) -> Result<bool, DynError> {
+    if !wait_available(conn, backend).await? {
+        return Ok(false);
+    }
+
+    let _ = conn.send(ServerMessage::status(format!(
+        "TESTS ----------\nBuilding the tests ({toolchain}). This may take a few time..."
+    ))).await;
+
+    backend.write_project(id, project, toolchain).await?;
+    debug!("Created the project files succesfully");
+
+    let (diagnostics_tx, diagnostics) = mpsc::unbounded_channel();
+    let Some(output) = forward_diagnostics(conn, project, backend.build_tests(id, toolchain, selection, diagnostics_tx), diagnostics, false).await? else {
+        return Ok(false);
+    };
+
+    let _r = conn.send(compilation_result(&output.log)).await;
+    if !output.status.success() {
+        return Err(RequestError::BuildFailed { what: "Test build", code: output.status.code(), log: output.log }.into());
+    }
+
+    let _ = conn.send(ServerMessage::status("RUNNING TESTS ----------")).await;
+
+    let mut binaries = output.tests;
+    binaries.sort_by(|a, b| a.suite.cmp(&b.suite));//src/lib.rs, src/main.rs, tests/...
+
+    let deadline = Instant::now() + limits.wall_time;
+    let mut summary = TestSummary::default();
+    let mut reason = TerminationReason::Exited { code: 0 };
+    for binary in &binaries {
+        let mut run = TestRun { suite: binary.suite.clone(), summary, running: None };
+        let child = backend.run_test(id, binary, selection, limits).await?;
+        let Some(binary_reason) = supervise(conn, backend, id, child, limits, deadline, StdoutFormat::Tests(&mut run)).await? else {
+            return Ok(false);
+        };
+
+        //the test running when a limit was hit failed because of it
+        if let Some(name) = run.running.take() {
+            let message = run.finished(TestResult {
+                name,
+                suite: binary.suite.clone(),
+                status: TestStatus::Failed,
+                duration_micros: None,
+                output: format!("stopped: {binary_reason}"),
+            });
+            let _ = conn.send(message).await;
+        }
+        summary = run.summary;
+
+        //failing tests exit with 101: keep the first failure
+        let limit_hit = !matches!(binary_reason, TerminationReason::Exited { .. });
+        if reason.is_success() {
+            reason = binary_reason;
+        }
+        if limit_hit {
+            break;
+        }
+    }
+
+    let _ = conn.send(ServerMessage::TestSummary { summary }).await;
+    Ok(report_finished(conn, Some(reason)).await)
+}
+
+/// Runs clippy on `project` and sends the lints to the client.
+///
+/// Returns `false` if the client left in the meantime.
+#[instrument(skip_all)]
+async fn lint(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: &Project,
+    toolchain: &Toolchain,
+    id: Uuid,
+) -> Result<bool, DynError> {
+    if !wait_available(conn, backend).await? {
+        return Ok(false);
+    }
+
+    let _ = conn.send(ServerMessage::status(format!(
+        "LINT ----------\nRunning clippy ({toolchain}). This may take a few time..."
+    ))).await;
+
+    backend.write_project(id, project, toolchain).await?;
+    debug!("Created the project files succesfully");
+
+    let (diagnostics_tx, diagnostics) = mpsc::unbounded_channel();
+    let Some(output) = forward_diagnostics(conn, project, backend.lint(id, toolchain, diagnostics_tx), diagnostics, true).await? else {
+        return Ok(false);
+    };
+
+    //a project that doesn't compile was reported through its diagnostics
+    let _r = conn.send(compilation_result(&output.log)).await;
+
+    Ok(true)
+}
+
+/// Compiles `project` up to `target` and sends it to the client in
+/// [`ServerMessage::InspectOutput`] chunks.
+///
+/// Returns `false` if the client left in the meantime.
+#[instrument(skip_all, fields(%target))]
+async fn inspect(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: &Project,
+    target: InspectTarget,
+    toolchain: &Toolchain,
+    build: &BuildOptions,
+    id: Uuid,
+) -> Result<bool, DynError> {
+    if !wait_available(conn, backend).await? {
+        return Ok(false);
+    }
+
+    let _ = conn.send(ServerMessage::status(format!(
+        "INSPECT ----------\nGenerating the {target} ({build}; {toolchain}). This may take a few time..."
+    ))).await;
+
+    backend.write_project(id, project, toolchain).await?;
+    debug!("Created the project files succesfully");
+
+    let (diagnostics_tx, diagnostics) = mpsc::unbounded_channel();
+    let inspection = backend.inspect(id, project, toolchain, build, target, diagnostics_tx);
+    let Some(output) = forward_diagnostics(conn, project, inspection, diagnostics, false).await? else {
+        return Ok(false);
+    };
+
+    let _r = conn.send(compilation_result(&output.log)).await;
+    if !output.status.success() {
+        return Err(RequestError::BuildFailed { what: "Inspect", code: output.status.code(), log: output.log }.into());
+    }
+
+    for data in chunks(&output.emitted, INSPECT_CHUNK_LEN) {
+        let _ = conn.send(ServerMessage::InspectOutput { target, data: data.to_string() }).await;
+    }
+    let _ = conn.send(ServerMessage::InspectFinished { target, truncated: output.emitted_truncated }).await;
+
+    Ok(true)
+}
+
+/// Splits `text` in parts of at most `max_len` bytes, at the end of a line
+/// when there's one.
+fn chunks(mut text: &str, max_len: usize) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut end = text.len().min(max_len);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end < text.len() && let Some(newline) = text[..end].rfind('\n') {
+            end = newline + 1;
+        }
+
+        let (chunk, rest) = text.split_at(end);
+        text = rest;
+        Some(chunk)
+    })
+}
+
+/// The log of a build for the client, cut at [`MAX_BUILD_LOG_LEN`] like the
+/// output of a run: the diagnostics were sent apart anyway.
+fn compilation_result(log: &str) -> ServerMessage {
+    if log.len() <= MAX_BUILD_LOG_LEN {
+        return ServerMessage::CompilationResult { output: log.to_string() };
+    }
+
+    let mut end = MAX_BUILD_LOG_LEN;
+    while !log.is_char_boundary(end) {
+        end -= 1;
+    }
+    ServerMessage::CompilationResult {
+        output: format!("{}\n... (log truncated, {} more bytes)", &log[..end], log.len() - end),
+    }
+}
+
+/// Forwards each diagnostic of `build` to the client as soon as it comes;
+/// with `lints`, the ones naming a lint are sent as [`ServerMessage::Lint`].
+///
+/// The build is stopped after [`BUILD_TIMEOUT`].
+///
+/// Returns `None` if the client left before the end of the build.
+async fn forward_diagnostics(
+    conn: &mut Connection,
+    project: &Project,
+    build: impl Future<Output = Result<BuildOutput, BackendError>>,
+    mut diagnostics: mpsc::UnboundedReceiver<Diagnostic>,
+    lints: bool,
+) -> Result<Option<BuildOutput>, DynError> {
+    let mut sent = Vec::new();//clippy --all-targets checks the code of the binary twice (bin + tests)
+    let mut message = |mut diagnostic: Diagnostic| {
+        if lints {
+            if sent.contains(&diagnostic) {
+                return None;
+            }
+            sent.push(diagnostic.clone());
+        }
+
+        match LintItem::from_diagnostic(&diagnostic) {
+            Some(lint) if lints => Some(ServerMessage::Lint { lint }),
+            _ => {
+                crates::add_dependency_hint(&mut diagnostic, &project.dependencies);
+                Some(ServerMessage::Diagnostic { diagnostic })
+            },
+        }
+    };
+
+    let started = Instant::now();
+    let deadline = sleep(BUILD_TIMEOUT);
+    tokio::pin!(build, deadline);
+    let output = loop {
+        select! {
+            output = &mut build => {
+                METRICS.observe_compile(started.elapsed());
+                break output?
+            },
+            _ = &mut deadline => {
+                let _ = conn.send(ServerMessage::error(
+                    format!("The build took more than {}s and was stopped", BUILD_TIMEOUT.as_secs())
+                )).await;
+                return Err(RequestError::BuildTimedOut.into());
+            },
+            Some(diagnostic) = diagnostics.recv() => {
+                if let Some(message) = message(diagnostic) {
+                    let _ = conn.send(message).await;
+                }
+            },
+            incoming = conn.recv() => {
+                if client_left(conn, incoming).await {
+                    return Ok(None);
+                }
+            },
+        }
+    };
+
+    //the build may end before every diagnostic was forwarded
+    while let Ok(diagnostic) = diagnostics.try_recv() {
+        if let Some(message) = message(diagnostic) {
+            let _ = conn.send(message).await;
+        }
+    }
+
+    Ok(Some(output))
+}
+
+/// Keeps the first part of a chunk of output that fits in `limits`.
+///
+/// Returns the part to forward and whether the limit was reached.
+fn limit_output(chunk: &[u8], written: &mut u64, limits: &ResourceLimits) -> (String, bool) {
+    let remaining = limits.max_output_bytes.saturating_sub(*written);
+    let len = chunk.len().min(remaining as usize);
+    *written += len as u64;
+
+    (String::from_utf8_lossy(&chunk[..len]).to_string(), len < chunk.len())
+}
+
+/// Runs the program built with `profile`; see [`supervise`].
+///
+/// Sends a [`ServerMessage::RunFinished`] telling why it stopped.
+/// Returns `false` if the client left before the program ended.
+#[instrument(skip_all)]
+pub async fn run(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    id: Uuid,
+    profile: BuildProfile,
+    limits: &ResourceLimits,
+) -> Result<bool, DynError> {
+    let _ = conn.send(ServerMessage::status("EXECUTION ----------")).await;
+
+    let child = backend.run(id, profile, limits).await?;
+    let reason = supervise(conn, backend, id, child, limits, Instant::now() + limits.wall_time, StdoutFormat::Raw).await?;
+    Ok(report_finished(conn, reason).await)
+}
+
+/// Runs `command` on the client's project; see [`supervise`].
+///
+/// Sends a [`ServerMessage::RunFinished`] telling why it stopped.
+/// Returns `false` if the client left before the command ended.
+#[instrument(name = "command", skip_all, fields(%command))]
+async fn cargo(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    project: &Project,
+    command: &CargoCommand,
+    toolchain: &Toolchain,
+    limits: &ResourceLimits,
+    id: Uuid,
+) -> Result<bool, DynError> {
+    if !wait_available(conn, backend).await? {
+        return Ok(false);
+    }
+
+    backend.write_project(id, project, toolchain).await?;
+    debug!("Created the project files succesfully");
+
+    let _ = conn.send(ServerMessage::status(format!("COMMAND ----------\n{command} ({toolchain})"))).await;
+
+    let child = backend.cargo(id, toolchain, command).await?;
+    let reason = supervise(conn, backend, id, child, limits, Instant::now() + limits.wall_time, StdoutFormat::Raw).await?;
+    Ok(report_finished(conn, reason).await)
+}
+
+/// How [`supervise`] forwards the stdout of a process.
+enum StdoutFormat<'a> {
+    /// As it comes, in [`ServerMessage::Stdout`] chunks.
+    Raw,
+    /// Events of a test harness (see [`libtest`]), sent as
+    /// [`ServerMessage::TestResult`]s.
+    Tests(&'a mut TestRun),
+}
+
+/// Progress of the tests of a binary.
+struct TestRun {
+    suite: String,
+    summary: TestSummary,
+    /// The test started and not yet over, if any.
+    running: Option<String>,
+}
+
+impl TestRun {
+    /// Turns a line printed by the binary into the message for the client.
+    fn message(&mut self, line: String) -> Option<ServerMessage> {
+        match libtest::parse_line(line.trim_end(), &self.suite) {
+            HarnessLine::Started(name) => {
+                self.running = Some(name);
+                None
+            },
+            HarnessLine::Finished(result) => {
+                self.running = None;
+                Some(self.finished(result))
+            },
+            HarnessLine::Event => None,
+            HarnessLine::Text => Some(ServerMessage::Stdout { data: line }),
+        }
+    }
+
+    fn finished(&mut self, result: TestResult) -> ServerMessage {
+        self.summary.add(result.status);
+        ServerMessage::TestResult { result }
+    }
+}
+
+/// Streams the stdout/stderr of `child` to the client and the client's input
+/// to its stdin, until it ends, `deadline` passes or it hits one of `limits`.
+///
+/// Returns why it stopped, or `None` if the client left before `child` ended.
+async fn supervise(
+    conn: &mut Connection,
+    backend: &dyn ExecutionBackend,
+    id: Uuid,
+    mut child: Child,
+    limits: &ResourceLimits,
+    deadline: Instant,
+    mut format: StdoutFormat<'_>,
+) -> Result<Option<TerminationReason>, DynError> {
+    let (Some(mut stdin), Some(mut stdout), Some(mut stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+        let _ = backend.kill(id, &mut child).await;
+        return Err("ERR_PLAYGROUND_RUN_TAKE_STDIOS".into());
+    };
+
+    let started = Instant::now();
+    let deadline = sleep_until(deadline);
+    tokio::pin!(deadline);
+
+    let mut stdout_buf = [0u8; OUTPUT_CHUNK_LEN];
+    let mut stderr_buf = [0u8; OUTPUT_CHUNK_LEN];
+    let mut stdout_open = true;
+    let mut stderr_open = true;
+    let mut written: u64 = 0;
+    let mut pending_line = String::new();//harness events are split across chunks
+    let mut out_of_memory = false;
+    //set when the server (or the client) decides to stop the program
+    let mut stopped: Option<TerminationReason> = None;
+
+    while (stdout_open || stderr_open) && stopped.is_none() {
+        select! {
+            //WALL TIME
+            _ = &mut deadline => stopped = Some(TerminationReason::TimedOut),
+            //READ STDOUT
+            read = stdout.read(&mut stdout_buf), if stdout_open => match read {
+                Ok(0) => {
+                    debug!("stdout pipe reached EOF");
+                    if !pending_line.is_empty() {
+                        let _ = conn.send(ServerMessage::Stdout { data: std::mem::take(&mut pending_line) }).await;
+                    }
+                    stdout_open = false;
+                },
+                Ok(n) => {
+                    let (s, exceeded) = limit_output(&stdout_buf[..n], &mut written, limits);
+                    trace!(data = %s, "stdout");
+                    match &mut format {
+                        StdoutFormat::Raw => {
+                            let _ = conn.send(ServerMessage::Stdout { data: s }).await;
+                        },
+                        StdoutFormat::Tests(run) => {
+                            pending_line.push_str(&s);
+                            while let Some(end) = pending_line.find('\n') {
+                                let line: String = pending_line.drain(..=end).collect();
+                                if let Some(message) = run.message(line) {
+                                    let _ = conn.send(message).await;
+                                }
+                            }
+                        },
+                    }
+                    if exceeded {
+                        stopped = Some(TerminationReason::OutputLimitExceeded);
+                    }
+                },
+                Err(e) => {
+                    error!(error = %e, "Error while reading stdout");
+                    let _ = conn.send(ServerMessage::error(e.to_string())).await;
+                    stdout_open = false;
+                },
+            },
+            //READ STDERR
+            read = stderr.read(&mut stderr_buf), if stderr_open => match read {
+                Ok(0) => {
+                    debug!("stderr pipe reached EOF");
+                    stderr_open = false;
+                },
+                Ok(n) => {
+                    let (s, exceeded) = limit_output(&stderr_buf[..n], &mut written, limits);
+                    trace!(data = %s, "stderr");
+                    out_of_memory |= s.contains(OOM_MARKER);
+                    let _ = conn.send(ServerMessage::Stderr { data: s }).await;
+                    if exceeded {
+                        stopped = Some(TerminationReason::OutputLimitExceeded);
+                    }
+                },
+                Err(e) => {
+                    error!(error = %e, "Error while reading stderr");
+                    let _ = conn.send(ServerMessage::error(e.to_string())).await;
+                    stderr_open = false;
+                },
+            },
+            //READ CLIENT
+            incoming = conn.recv() => match incoming {
+                Incoming::Message(ClientMessage::Input { mut data }) => {
+                    //important since no input is processed if there is not a '\n'
+                    if !data.ends_with('\n') {
+                        data.push('\n');
+                    }
+
+                    if stdin.write_all(data.as_bytes()).await.is_err() {
+                        error!("ERR_PLAYGROUND_FORWARD_STDIN");
+                        let _ = conn.send(ServerMessage::error("ERR_PLAYGROUND_FORWARD_STDIN")).await;
+                    }
+                },
+                Incoming::Message(ClientMessage::Exit) => stopped = Some(TerminationReason::KilledByUser),
+                incoming => {
+                    if client_left(conn, incoming).await {
+                        let _ = backend.kill(id, &mut child).await;
+                        return Ok(None);
+                    }
+                },
+            },
+        }
+    }
+
+    //pipes closed doesn't mean the program is over: the deadline still applies
+    let reason = match stopped {
+        Some(reason) => reason,
+        None => select! {
+            status = child.wait() => {
+                let status = status.map_err(|_| "ERR_PLAYGROUND_RUN_CHILD_WAIT")?;
+                match backend.termination_reason(status) {
+                    reason if out_of_memory && !reason.is_success() => TerminationReason::OutOfMemory,
+                    reason => reason,
+                }
+            },
+            _ = &mut deadline => TerminationReason::TimedOut,
+        },
+    };
+
+    //stopped by a limit or by the user
+    if let Ok(None) = child.try_wait() {
+        if let Err(_err) = backend.kill(id, &mut child).await {
+            error!(error = %_err, "kill failed");
+        }
+        let _ = child.wait().await;
+    }
+
+    METRICS.observe_run(started.elapsed());
+    Ok(Some(reason))
+}
+
+/// Tells the client why the run ended, unless it left (`reason` is `None`).
+///
+/// Returns whether the client is still connected.
+async fn report_finished(conn: &mut Connection, reason: Option<TerminationReason>) -> bool {
+    let Some(reason) = reason else {
+        return false;
+    };
+
+    info!(%reason, "run finished");
+    let _ = conn.send(ServerMessage::RunFinished { reason }).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_logs_fit_in_a_frame() {
+        let log = "warning: unused variable `é`\n".repeat(MAX_FRAME_LEN / 8);
+        let ServerMessage::CompilationResult { output } = compilation_result(&log) else {
+            panic!("not a compilation result");
+        };
+        assert!(output.starts_with("warning: unused variable"));
+        assert!(output.ends_with("more bytes)"));
+        assert!(encode_message(&ServerMessage::CompilationResult { output }).is_ok());
+
+        assert_eq!(compilation_result("ok"), ServerMessage::CompilationResult { output: "ok".to_string() });
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::quotas::UserKey;
+
+/// Used for wait estimates until the first session has ended.
+const DEFAULT_SESSION_DURATION: Duration = Duration::from_secs(30);
+/// Weight of the last session in the running average of session durations.
+const DURATION_SMOOTHING: f64 = 0.2;
+
+/// Keeps track of live sessions and of the clients waiting for a free slot.
+///
+/// Clients are admitted in FIFO order: a newcomer never overtakes someone
+/// already waiting, even if a slot frees up right when it connects.
+///
+/// A user (see [`UserKey`]) can only have so many sessions, live or waiting,
+/// so that one can't take every slot.
+pub struct SessionRegistry {
+    max_sessions: usize,
+    max_waiting: usize,
+    max_per_user: usize,
+    state: Mutex<RegistryState>,
+    changed: watch::Sender<u64>,//bumped every time a slot or a queue position changes
+}
+
+struct RegistryState {
+    active: usize,
+    waiting: VecDeque<Uuid>,
+    users: HashMap<UserKey, usize>,//sessions of every user, live or waiting
+    avg_session: Duration,
+}
+
+pub enum Admission {
+    Admitted(SessionGuard),
+    Queued(QueueTicket),
+    /// Both the slots and the waiting queue are full.
+    Rejected,
+    /// The user already has as many sessions as allowed.
+    TooManySessions,
+}
+
+/// A live session; frees its slot when dropped.
+pub struct SessionGuard {
+    registry: Arc<SessionRegistry>,
+    user: UserKey,
+    started: Instant,
+}
+
+/// A place in the waiting queue; leaves the queue when dropped.
+pub struct QueueTicket {
+    registry: Arc<SessionRegistry>,
+    user: UserKey,
+    id: Uuid,
+    changes: watch::Receiver<u64>,
+    admitted: bool,
+}
+
+impl SessionRegistry {
+    pub fn new(max_sessions: usize, max_waiting: usize, max_per_user: usize) -> Arc<Self> {
+        let (changed, _) = watch::channel(0);
+
+        Arc::new(SessionRegistry {
+            max_sessions,
+            max_waiting,
+            max_per_user,
+            state: Mutex::new(RegistryState {
+                active: 0,
+                waiting: VecDeque::new(),
+                users: HashMap::new(),
+                avg_session: DEFAULT_SESSION_DURATION,
+            }),
+            changed,
+        })
+    }
+
+    pub fn join(self: &Arc<Self>, id: Uuid, user: UserKey) -> Admission {
+        let mut state = self.state.lock().unwrap();
+
+        if state.users.get(&user).is_some_and(|sessions| *sessions >= self.max_per_user) {
+            return Admission::TooManySessions;
+        }
+
+        if state.waiting.is_empty() && state.active < self.max_sessions {
+            state.active += 1;
+            *state.users.entry(user.clone()).or_default() += 1;
+            return Admission::Admitted(SessionGuard::new(self, user));
+        }
+
+        if state.waiting.len() >= self.max_waiting {
+            return Admission::Rejected;
+        }
+
+        state.waiting.push_back(id);
+        *state.users.entry(user.clone()).or_default() += 1;
+        Admission::Queued(QueueTicket {
+            registry: Arc::clone(self),
+            user,
+            id,
+            changes: self.changed.subscribe(),
+            admitted: false,
+        })
+    }
+
+    pub fn active_sessions(&self) -> usize {
+        self.state.lock().unwrap().active
+    }
+
+    pub fn waiting_sessions(&self) -> usize {
+        self.state.lock().unwrap().waiting.len()
+    }
+
+    fn notify(&self) {
+        self.changed.send_modify(|generation| *generation = generation.wrapping_add(1));
+    }
+}
+
+impl RegistryState {
+    /// One session less for `user`, live or waiting.
+    fn leave(&mut self, user: &UserKey) {
+        if let Some(sessions) = self.users.get_mut(user) {
+            *sessions -= 1;
+            if *sessions == 0 {
+                self.users.remove(user);
+            }
+        }
+    }
+}
+
+impl SessionGuard {
+    fn new(registry: &Arc<SessionRegistry>, user: UserKey) -> Self {
+        SessionGuard { registry: Arc::clone(registry), user, started: Instant::now() }
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        {
+            let mut state = self.registry.state.lock().unwrap();
+            state.active -= 1;
+            state.leave(&self.user);
+
+            let last = self.started.elapsed().as_secs_f64();
+            let avg = state.avg_session.as_secs_f64();
+            state.avg_session = Duration::from_secs_f64(
+                avg + DURATION_SMOOTHING * (last - avg)
+            );
+        }
+        self.registry.notify();
+    }
+}
+
+impl QueueTicket {
+    /// Takes a free slot if this ticket is first in line.
+    pub fn try_admit(&mut self) -> Option<SessionGuard> {
+        {
+            let mut state = self.registry.state.lock().unwrap();
+
+            if state.waiting.front() != Some(&self.id) || state.active >= self.registry.max_sessions {
+                return None;
+            }
+
+            state.waiting.pop_front();
+            state.active += 1;
+            self.admitted = true;
+        }
+        self.registry.notify();//everyone behind moved up by one
+
+        Some(SessionGuard::new(&self.registry, self.user.clone()))
+    }
+
+    /// 1-based position in the queue and a rough estimate of the wait.
+    pub fn position(&self) -> (usize, Duration) {
+        let state = self.registry.state.lock().unwrap();
+        let position = state.waiting.iter().position(|id| *id == self.id).unwrap_or(0) + 1;
+
+        //every slot serves roughly one session per 'avg_session'
+        let rounds = position.div_ceil(self.registry.max_sessions.max(1)) as u32;
+        (position, state.avg_session * rounds)
+    }
+
+    /// Resolves the next time a slot or a queue position changes.
+    pub async fn changed(&mut self) {
+        if self.changes.changed().await.is_err() {
+            std::future::pending::<()>().await;//registry gone: nothing will ever change
+        }
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        if self.admitted {
+            return;
+        }
+
+        {
+            let mut state = self.registry.state.lock().unwrap();
+            state.waiting.retain(|id| *id != self.id);
+            state.leave(&self.user);
+        }
+        self.registry.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    /// A new user every time.
+    fn someone() -> UserKey {
+        UserKey::Account(Uuid::new_v4().to_string())
+    }
+
+    fn admitted(admission: Admission) -> SessionGuard {
+        match admission {
+            Admission::Admitted(guard) => guard,
+            _ => panic!("not admitted"),
+        }
+    }
+
+    fn queued(admission: Admission) -> QueueTicket {
+        match admission {
+            Admission::Queued(ticket) => ticket,
+            _ => panic!("not queued"),
+        }
+    }
+
+    #[test]
+    fn sessions_are_admitted_up_to_the_limit() {
+        let registry = SessionRegistry::new(2, 1, 1);
+
+        let first = admitted(registry.join(Uuid::new_v4(), someone()));
+        let _second = admitted(registry.join(Uuid::new_v4(), someone()));
+        assert_eq!(registry.active_sessions(), 2);
+
+        let mut third = queued(registry.join(Uuid::new_v4(), someone()));
+        assert!(matches!(registry.join(Uuid::new_v4(), someone()), Admission::Rejected));//queue full too
+        assert!(third.try_admit().is_none());
+
+        //the slot is freed with its guard
+        drop(first);
+        assert_eq!(registry.active_sessions(), 1);
+        let _third = third.try_admit().unwrap();
+        assert_eq!((registry.active_sessions(), registry.waiting_sessions()), (2, 0));
+    }
+
+    #[test]
+    fn waiting_clients_are_served_in_order() {
+        let registry = SessionRegistry::new(1, 3, 1);
+        let running = admitted(registry.join(Uuid::new_v4(), someone()));
+
+        let mut first = queued(registry.join(Uuid::new_v4(), someone()));
+        let second = queued(registry.join(Uuid::new_v4(), someone()));
+        let mut third = queued(registry.join(Uuid::new_v4(), someone()));
+        assert_eq!(first.position().0, 1);
+        assert_eq!(second.position().0, 2);
+        assert_eq!(third.position().0, 3);
+
+        //whoever leaves the queue, the ones behind move up
+        drop(second);
+        assert_eq!(registry.waiting_sessions(), 2);
+        assert_eq!(third.position().0, 2);
+
+        //a free slot goes to the first in line only, even with a newcomer
+        drop(running);
+        assert!(third.try_admit().is_none());
+        assert!(matches!(registry.join(Uuid::new_v4(), someone()), Admission::Queued(_)));
+        let _first = first.try_admit().unwrap();
+        assert_eq!(third.position().0, 1);
+    }
+
+    #[test]
+    fn users_have_a_limited_number_of_sessions() {
+        let registry = SessionRegistry::new(1, 3, 2);
+        let anonymous = UserKey::Address(Ipv4Addr::LOCALHOST.into());
+
+        let running = admitted(registry.join(Uuid::new_v4(), anonymous.clone()));
+        let waiting = queued(registry.join(Uuid::new_v4(), anonymous.clone()));
+        assert!(matches!(registry.join(Uuid::new_v4(), anonymous.clone()), Admission::TooManySessions));
+        assert!(matches!(registry.join(Uuid::new_v4(), someone()), Admission::Queued(_)));
+
+        //leaving the queue or ending a session gives the place back
+        drop(waiting);
+        let mut waiting = queued(registry.join(Uuid::new_v4(), anonymous.clone()));
+        drop(running);
+        let _running = waiting.try_admit().unwrap();
+        let _waiting = queued(registry.join(Uuid::new_v4(), anonymous.clone()));
+        assert!(matches!(registry.join(Uuid::new_v4(), anonymous), Admission::TooManySessions));
+    }
+}
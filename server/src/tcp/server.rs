@@ -1,661 +1,633 @@
-use std::{
-    io::{self, ErrorKind, Write},
-    net::{TcpListener, TcpStream},
-    process::{Command, Stdio},
-    sync::{Arc, Mutex},
-    collections::VecDeque,
-    time::Duration,
-    task::Poll,
-    pin::Pin,
-    thread
-};
+use std::{future::Future, io, sync::Arc, time::Duration};
 
 use tokio::{
-    io::{AsyncRead, AsyncWriteExt, ReadBuf},
-    process::Command as tokio_command
+    net::{TcpListener, TcpStream},
+    select,
+    time::{timeout, timeout_at, Instant},
 };
-
-use uuid::Uuid;//generate ids for socket representation (used on containers)
-use futures::future::poll_fn;
-
-use crate::models::{
-    models::*,
-    lib::*
+use tokio_rustls::TlsAcceptor;
+use tokio_util::{codec::Framed, task::TaskTracker};
+
+use tracing::{error, field, info, info_span, warn, Instrument, Span};
+use uuid::Uuid;//generate ids for socket representation (used on backends)
+
+use playground_protocol::*;
+
+use crate::{
+    auth::Authenticator,
+    backend::{crates, ExecutionBackend, TestSelection},
+    config::ServerConfig,
+    metrics::{self, Failure, METRICS},
+    quotas::{Quotas, RunCharge, UserKey},
+    snippets::{SnippetStore, SnippetStoreError},
+    tcp::{
+        connection::{Connection, Incoming, Transport},
+        pipeline::{self, DynError},
+        registry::{Admission, QueueTicket, SessionGuard, SessionRegistry},
+        shutdown::Shutdown,
+        tls,
+        websocket,
+    },
 };
 
+/// Once the grace period is over, how long the sessions still have to stop
+/// their requests and clean the backend.
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(30);
+const SHUTDOWN_REASON: &str = "The server is shutting down";
+/// How long a new client has to say hello; it takes no slot until then.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+pub async fn handle_client(
+    mut conn: Connection,
+    id: Uuid,
+    backend: Arc<dyn ExecutionBackend>,
+    snippets: Arc<SnippetStore>,
+    quotas: Arc<Quotas>,
+    account: Option<SessionClaims>,//set by the handshake, for the whole session
+) {
+    loop {
+        //no new request once the server is going down
+        if conn.shutdown().is_started() {
+            let _ = conn.send(ServerMessage::exit(SHUTDOWN_REASON)).await;
+            break;
+        }
 
+        let msg = match conn.recv().await {
+            Incoming::Message(msg) => msg,
+            Incoming::Corrupted(reason) => {
+                METRICS.failed(Failure::InvalidRequest);
+                warn!(%reason, "corrupted request");
+                let _ = conn.send(ServerMessage::RequestCorrupted { reason }).await;
+                continue;
+            },
+            Incoming::ShuttingDown => continue,
+            Incoming::Closed => break,
+        };
 
-const SERVER_ADDRESS: &str = "127.0.0.1:8000";
-const BUILDER_CONTAINER_NAME: &str = "ruscompy";
-const RUNNER_CONTAINER_NAME: &str = "ruruny";
-const MAX_CLIENTS: u8 = 10;//accept max n clients
+        info!(request = %msg, "request received");
 
+        match msg {
+            //the session was admitted and is charged as the account of the handshake
+            ClientMessage::Hello { .. } => {
+                METRICS.failed(Failure::InvalidRequest);
+                let _ = conn.send(ServerMessage::error("Already greeted: open a new connection to change account")).await;
+            },
+            ClientMessage::Exit => break,
+            ClientMessage::RunCompile { project, toolchain, build, limits } => {
+                if !validate_request(&mut conn, backend.as_ref(), &project, &toolchain).await {
+                    continue;
+                }
+                //runs count against the limits of the user, for as long as they last
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-pub fn handle_client(mut stream: TcpStream, id: Uuid) {
-    let mut shutdown = false;
-    let _r = stream.set_nonblocking(true);
-    
-    let mut server_res: VecDeque<JsonInfo>= VecDeque::new();
+                //tell the backend
+                if !pipeline::run_compile(&mut conn, backend.as_ref(), project, toolchain, build, limits, id).await {
+                    break;
+                }
+            },
+            ClientMessage::Command { command, project, toolchain, limits } => {
+                if let Err(e) = command.validate() {
+                    METRICS.failed(Failure::InvalidRequest);
+                    let _ = conn.send(ServerMessage::error(format!("Invalid command: {e}"))).await;
+                    continue;
+                }
+                if !validate_request(&mut conn, backend.as_ref(), &project, &toolchain).await {
+                    continue;
+                }
+                //runs count against the limits of the user, for as long as they last
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-    while !shutdown {
-        //READ ----------------------------------------
-        loop {
-            match read_json_info(&mut stream) {
-                Ok(parsed_data) => {
-                    println!("[client]: {parsed_data}");
+                if !pipeline::run_command(&mut conn, backend.as_ref(), project, command, toolchain, limits, id).await {
+                    break;
+                }
+            },
+            ClientMessage::Test { project, toolchain, limits } => {
+                if !validate_request(&mut conn, backend.as_ref(), &project, &toolchain).await {
+                    continue;
+                }
+                //runs count against the limits of the user, for as long as they last
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-                    if parsed_data.header == "exit" {
-                        shutdown = true;
-                        break;
-                    } else if parsed_data.header == "run&compile" {
-                        //tell docker
-                        stream = docker_handler(stream, parsed_data.body, id);
-                    }
-                },
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                        match e.kind() {
-                            ErrorKind::WouldBlock => {//non-block error
-                                break;
-                            },
-                            _ => {//severe error
-                                eprintln!("Server error while listening: {e}");
-                                shutdown = true;
-                                break;
-                            },
-                        }
-                    } else {//non severe error
-                        eprintln!("{e}");
-                    }
-                },
-            }
-        }
-        
-        //WRITE ----------------------------------------
-        while let Some(res) = server_res.pop_front() {//foreach JsonInfo needed to be sent
-            match write_json_info(&mut stream, res) {
-                Ok(_ok) => {
-                    // smth
-                },
-                Err(e) => {
-                    if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                        match e.kind() {
-                            ErrorKind::WouldBlock => {//non-block error
-                                // smth
-                            },
-                            _ => {//severe error
-                                eprintln!("Server error while writing: {e}");
-                                shutdown = true;
-                                break;
-                            },
-                        }
-                    } else {//non severe error
-                        eprintln!("{e}");
-                    }
-                },
-            }
-        }
-        
-        thread::sleep(Duration::from_millis(200));
-    }
-}
+                if !pipeline::run_tests(&mut conn, backend.as_ref(), project, toolchain, TestSelection::default(), limits, id).await {
+                    break;
+                }
+            },
+            ClientMessage::Lint { project, toolchain } => {
+                if !validate_request(&mut conn, backend.as_ref(), &project, &toolchain).await {
+                    continue;
+                }
+                //runs count against the limits of the user, for as long as they last
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-fn is_req_shutdown(stream: &mut TcpStream) -> bool {
-    match read_json_info(stream) {
-        Ok(parsed_data) => {
-            parsed_data.header == "exit"
-        }
-        Err(e) => {
-            if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                match e.kind() {
-                    ErrorKind::WouldBlock => {//non-block error
-                        false
-                    },
-                    _ => {//severe error
-                        eprintln!("Server error while listening: {e}");
-                        true
-                    },
+                if !pipeline::run_lint(&mut conn, backend.as_ref(), project, toolchain, id).await {
+                    break;
                 }
-            } else {
-                false
-            }
-        }
-    }
-}
+            },
+            ClientMessage::Inspect { project, target, toolchain, build } => {
+                if !validate_request(&mut conn, backend.as_ref(), &project, &toolchain).await {
+                    continue;
+                }
+                //runs count against the limits of the user, for as long as they last
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-pub fn docker_handler(mut stream: TcpStream, body: String, id: Uuid) -> TcpStream {
-    match docker_compile(&mut stream, body, id) {
-        Ok(_ok) => {
-            match docker_run(&mut stream, id) {
-                Ok(_ok) => {
-                    // smth
+                if !pipeline::run_inspect(&mut conn, backend.as_ref(), project, target, toolchain, build, id).await {
+                    break;
                 }
-                Err(_err) => {
-                    eprintln!("error during run: {_err}");
+            },
+            ClientMessage::ListCrates => {
+                let _ = conn.send(ServerMessage::CrateList { crates: crates::crate_list() }).await;
+            },
+            ClientMessage::Share { mut snippet } => {
+                //only a verified account can sign a snippet
+                snippet.author = account.as_ref().map(|claims| claims.login.clone());
+                if let Err(e) = check_snippet(&snippet) {
+                    METRICS.failed(Failure::InvalidRequest);
+                    let _ = conn.send(ServerMessage::error(format!("Invalid snippet: {e}"))).await;
+                    continue;
                 }
-            }
-        }
-        Err(_err) => {
-            println!("error during compile: {_err}");
-        }
-    }
+                //every snippet takes room on the disk for good
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
+
+                let message = match snippets.save(&snippet).await {
+                    Ok(id) => ServerMessage::Shared { id },
+                    Err(SnippetStoreError::Full { .. }) => {
+                        METRICS.failed(Failure::Snippets);
+                        warn!("snippet store full");
+                        ServerMessage::error("No more snippets can be shared for now")
+                    },
+                    Err(_err) => {
+                        METRICS.failed(Failure::Snippets);
+                        error!(error = %_err, "snippet store failed");
+                        ServerMessage::error("Could not share the snippet, try again later")
+                    },
+                };
+                let _ = conn.send(message).await;
+            },
+            ClientMessage::LoadSnippet { id } => {
+                let message = match snippets.load(&id).await {
+                    Ok(Some(snippet)) => ServerMessage::Snippet { id, snippet },
+                    Ok(None) => ServerMessage::error(format!("Snippet '{id}' not found")),
+                    Err(_err) => {
+                        METRICS.failed(Failure::Snippets);
+                        error!(error = %_err, "snippet store failed");
+                        ServerMessage::error("Could not load the snippet, try again later")
+                    },
+                };
+                let _ = conn.send(message).await;
+            },
+            ClientMessage::Format { source, edition } => {
+                if source.len() > MAX_FILE_LEN {
+                    METRICS.failed(Failure::InvalidRequest);
+                    let _ = conn.send(ServerMessage::error(format!(
+                        "Invalid source: {} bytes, at most {MAX_FILE_LEN} are allowed", source.len()
+                    ))).await;
+                    continue;
+                }
+                let Some(_run) = start_run(&mut conn, &quotas, account.as_ref()).await else {
+                    continue;
+                };
 
-    match docker_clean_compile(id) {
-        Ok(_ok) => {
-            println!("COMPILER container succesfully cleaned!");
-        }
-        Err(_err) => {
-            eprintln!("{_err}");
+                pipeline::format(&mut conn, backend.as_ref(), source, edition).await;
+            },
+            ClientMessage::Input { .. } => {
+                let _ = conn.send(ServerMessage::error("No program is running")).await;
+            },
         }
     }
 
-    match docker_clean_run(id) {
-        Ok(_ok) => {
-            println!("RUNNER container succesfully cleaned!");
-        }
-        Err(_err) => {
-            eprintln!("{_err}");
-        }
+    //the grace period ended during a request, which was stopped
+    if conn.shutdown().is_expired() {
+        let _ = conn.send(ServerMessage::exit("The server shut down before the request was over")).await;
     }
-
-    // send exit
-    let _o = write_json_info(
-        &mut stream,
-        JsonInfo::from("exit", "gracefully exit")
-    );
-
-    stream
 }
 
-pub fn docker_compile(stream: &mut TcpStream, body: String, id: Uuid) -> Result<&str, Box<dyn std::error::Error>> {
-    let mut retry_counter: u8 = 0;
+/// Waits for the hello of a new client, for at most `within`.
+///
+/// Returns the account of the session, or `Err` once the client was told
+/// why it can't go on.
+async fn greet(conn: &mut Connection, auth: &Authenticator, within: Duration) -> Result<Option<SessionClaims>, ()> {
+    let deadline = Instant::now() + within;
 
-    //wait until 'compiler' & 'runner' containers are up
     loop {
-        let output = Command::new("docker")
-            .args(["ps"])
-            .output();
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-
-                if stdout.contains(BUILDER_CONTAINER_NAME) && stdout.contains(BUILDER_CONTAINER_NAME) {
-                    break;
-
-                } else {
-                    println!(
-                        "Container '{}' OR '{}' currently unavailable",
-                        BUILDER_CONTAINER_NAME,
-                        RUNNER_CONTAINER_NAME
-                    );
-
-                    if retry_counter < 10 {
-                        println!("Retrying in 3s...");
-                        thread::sleep(Duration::from_millis(3000));
-                        retry_counter = retry_counter + 1;
-                    } else {
-                        let _o = write_json_info(
-                            stream,
-                            JsonInfo::from("error", "Request aborted due to exceed on time limit")
-                        );
-                        return Err("Request aborted due to exceed on time limit".into());
-                    }
-                    
-                }
+        let Ok(incoming) = timeout_at(deadline, conn.recv()).await else {
+            METRICS.failed(Failure::Timeout);
+            warn!("no hello received in time");
+            let _ = conn.send(ServerMessage::exit(format!("No hello received within {within:?}"))).await;
+            return Err(());
+        };
+
+        match incoming {
+            Incoming::Message(ClientMessage::Hello { protocol_version, token }) => {
+                return hello(conn, auth, protocol_version, token.as_deref()).await;
             },
-            Err(_err) => {
-                println!("{_err}");
-
-                if retry_counter < 10 {
-                    println!("Retrying in 3s...");
-                    thread::sleep(Duration::from_millis(3000));
-                    retry_counter = retry_counter + 1;
-                } else {
-                    return Err("Request aborted due to exceed on time limit".into());
-                }
+            Incoming::Message(ClientMessage::Exit) | Incoming::Closed => return Err(()),
+            Incoming::ShuttingDown => {
+                let _ = conn.send(ServerMessage::exit(SHUTDOWN_REASON)).await;
+                return Err(());
+            },
+            Incoming::Message(_) => {
+                METRICS.failed(Failure::InvalidRequest);
+                let _ = conn.send(ServerMessage::error("Handshake required: send hello first")).await;
+            },
+            Incoming::Corrupted(reason) => {
+                METRICS.failed(Failure::InvalidRequest);
+                warn!(%reason, "corrupted request");
+                let _ = conn.send(ServerMessage::RequestCorrupted { reason }).await;
             },
         }
     }
+}
 
-    if is_req_shutdown(stream) {
-        return Err("client requested shutdown prematurely".into());
+/// Answers a hello: checks the protocol version and the token, then welcomes
+/// the client with its account.
+async fn hello(
+    conn: &mut Connection,
+    auth: &Authenticator,
+    protocol_version: u32,
+    token: Option<&str>,
+) -> Result<Option<SessionClaims>, ()> {
+    if protocol_version != PROTOCOL_VERSION {
+        METRICS.failed(Failure::InvalidRequest);
+        let _ = conn.send(ServerMessage::error(format!(
+            "Unsupported protocol version {protocol_version} (server speaks v{PROTOCOL_VERSION})"
+        ))).await;
+        let _ = conn.send(ServerMessage::exit("protocol version mismatch")).await;
+        return Err(());
     }
 
-    let _ = write_json_info(stream, JsonInfo::from(
-        "",
-        "REQUEST STATUS ----------\nBuilding the file release. This may take a few time..."
-    ));
-
-    //insert client body in a new .rs:
-    let child = Command::new("docker")
-        .args([
-            "exec",
-            "-i",//necessary when using pipes
-            BUILDER_CONTAINER_NAME,
-            "sh",
-            "-c",
-            &format!("cat > ./src/bin/{}", id.to_string()+".rs"),//write smth (cat) inside a file ({})
-        ])
-        .stdin(Stdio::piped())
-        .spawn();
-
-    match child {
-        Ok(mut child) => {
-            if let Some(mut stdin) = child.stdin.take() {
-                match stdin.write_all(body.as_bytes()) {
-                    Ok(_ok) => {},
-                    Err(_err) => {
-                        return Err("ERR_PLAYGROUND_WRITE_CLIENTFILE.RS".into());
-                    },
-                }
-            }
-        
-            match child.wait() {
-                Ok(output) => {
-                    if output.success() {
-                        println!("Created clientfile.rs succesfully");
-                    } else {
-                        return Err("ERR_PLAYGROUND_WAIT_CREATE_CLIENTFILE.RS".into());
-                    }
-                }
-                Err(_) => {
-                    return Err("ERR_PLAYGROUND_WAIT_CREATE_CLIENTFILE.RS".into());
-                }
+    match auth.authenticate(token) {
+        Ok(claims) => {
+            if let Some(claims) = &claims {
+                Span::current().record("user", claims.login.as_str());
+                info!(role = claims.role, "Client authenticated");
             }
-        }
-        Err(_err) => {
-            return Err("ERR_PLAYGROUND_CREATE_CLIENTFILE.RS".into());
-        }
+            let _ = conn.send(ServerMessage::welcome(claims.as_ref().map(|claims| claims.login.clone()))).await;
+            Ok(claims)
+        },
+        Err(e) => {
+            METRICS.failed(Failure::Unauthenticated);
+            warn!(error = %e, "authentication failed");
+            let _ = conn.send(ServerMessage::error(format!("Authentication failed: {e}"))).await;
+            let _ = conn.send(ServerMessage::exit("authentication failed")).await;
+            Err(())
+        },
     }
+}
 
-    if is_req_shutdown(stream) {
-        return Err("client requested shutdown prematurely".into());
+/// Takes a run from the quotas of the user of the session, telling the
+/// client when it hit one of its limits.
+async fn start_run(conn: &mut Connection, quotas: &Arc<Quotas>, account: Option<&SessionClaims>) -> Option<RunCharge> {
+    match quotas.start_run(account, conn.peer_addr().ip()) {
+        Ok(run) => Some(run),
+        Err(limited) => {
+            METRICS.failed(Failure::RateLimited);
+            warn!(%limited, "rate limited");
+            let _ = conn.send(ServerMessage::RateLimited {
+                message: limited.to_string(),
+                retry_after_secs: limited.retry_after_secs(),
+            }).await;
+            None
+        },
     }
+}
 
-    //cargo build --release => get exe + more oredered (show compile problems then if ok execute)
-    let child = Command::new("docker")
-        .args([
-            "exec",
-            "-i",
-            BUILDER_CONTAINER_NAME,
-            "sh",
-            "-c",
-            &format!("cargo build --release --bin {id}")
-        ])
-        .output();
-
-    match child {
-        Ok(output) => {
-            let out_str = String::from_utf8_lossy(&output.stderr);
-
-            eprintln!("{out_str}");
-
-            let _r = write_json_info(
-                stream, JsonInfo::from("compilation_result", &out_str)
-            );
-
-            let output_code_status = output.status.code().unwrap();
-            if output_code_status != 0 {
-                return Err(
-                    format!(
-                        "Build failed with status: {}\n{:?}",
-                        output_code_status,
-                        String::from_utf8_lossy(&output.stderr)
-                    ).into()
-                );
-            }
-            
+/// Checks the project and the toolchain of a request, telling the client
+/// what's wrong with them.
+async fn validate_request(conn: &mut Connection, backend: &dyn ExecutionBackend, project: &Project, toolchain: &Toolchain) -> bool {
+    let error = match check_project(project) {
+        Err(e) => format!("Invalid project: {e}"),
+        Ok(()) => match check_toolchain(backend, toolchain).await {
+            Err(e) => format!("Invalid toolchain: {e}"),
+            Ok(()) => return true,
         },
-        Err(_err) => {
-            return Err("ERR_PLAYGROUND_CARGORUSTC".into());
-        }
-    }
+    };
 
-    //copy .exe from COMPILER to shared VOLUME
-    let output = Command::new("docker")
-        .args([
-            "exec",
-            BUILDER_CONTAINER_NAME,
-            "sh",
-            "-c",
-            &format!("cp ./target/release/{id} ../shared_folder/{id}")
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if !output.status.success() {
-                eprintln!("Failed to copy file: {}", String::from_utf8_lossy(&output.stderr));
-            }
-        }
-        Err(_err) => {
-            eprintln!("ERR_PLAYGROUND_CP_EXE");
-        }
-    }
+    METRICS.failed(Failure::InvalidRequest);
+    let _ = conn.send(ServerMessage::error(error)).await;
+    false
+}
 
-    Ok("Build created succesfully")
+/// Checks the limits of `project` and that it only uses available crates.
+fn check_project(project: &Project) -> Result<(), DynError> {
+    project.validate()?;
+    crates::check_dependencies(&project.dependencies)?;
+    Ok(())
 }
 
-pub fn docker_clean_compile(id: Uuid) -> Result<String, Box<dyn std::error::Error>> {
-    //WARNING: may not clear everything on container; be sure to run a deep cleaning script every run
-    //remove .rs + exe
-    docker_rm_file(BUILDER_CONTAINER_NAME, &format!("./src/bin/{id}.rs"))?;
-    docker_rm_file(BUILDER_CONTAINER_NAME, &format!("./target/release/{id}"))?;
-    docker_rm_file(BUILDER_CONTAINER_NAME, &format!("./target/release/{id}.d"))?;
-    
-    Ok("Ok".to_string())
+/// Checks the limits of `snippet` and that its project only uses available crates.
+fn check_snippet(snippet: &Snippet) -> Result<(), DynError> {
+    snippet.validate()?;
+    crates::check_dependencies(&snippet.project.dependencies)?;
+    Ok(())
 }
 
-pub async fn consume_ready<T>(stream: &mut T) -> Result<Vec<u8>, std::io::Error> where T: AsyncRead + Unpin {
-    let mut pinned = Pin::new(stream);//target to evalutate
-    
-    poll_fn(|cx| {
-        let mut buf = [0; 1024];
-        let mut readbuf = ReadBuf::new(&mut buf);
-
-        match pinned.as_mut().poll_read(cx, &mut readbuf) {//read from target
-            Poll::Ready(Ok(())) => {//OK -> return its content
-                let filled = readbuf.filled();
-                if !filled.is_empty() {
-                    return Poll::Ready(Ok(filled.to_vec()));
-                } else {
-                    return Poll::Ready(Ok("EOF".as_bytes().to_vec()));
-                }
-            }
-            Poll::Ready(Err(e)) => {//Err -> forward it
-                eprintln!("Error: {}", e);
-                return Poll::Ready(Err(e));
-            }
-            Poll::Pending => {//Pending -> target does not have produced output yet: return ''
-                return Poll::Ready(Ok(Vec::new()));
-            }
-        }
-    }).await
+/// Checks that the channel of `toolchain` is installed on the backend.
+async fn check_toolchain(backend: &dyn ExecutionBackend, toolchain: &Toolchain) -> Result<(), DynError> {
+    let channels = backend.channels().await?;
+    if channels.contains(&toolchain.channel) {
+        return Ok(());
+    }
+
+    let available: Vec<&str> = channels.iter().map(Channel::as_str).collect();
+    Err(format!("'{}' is not installed on the server (available: {})", toolchain.channel, available.join(", ")).into())
 }
 
-pub fn docker_run(mut stream: &mut TcpStream, id: Uuid) -> Result<String, Box<dyn std::error::Error>> {
-    let _ = write_json_info(stream, JsonInfo::from("", "EXECUTION ----------"));
-
-    //copy from VOLUME to RUNNER
-    let output = Command::new("docker")
-        .args([
-            "exec",
-            RUNNER_CONTAINER_NAME,
-            "sh",
-            "-c",
-            &format!("cp ../shared_folder/{id} ./{id}")
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if !output.status.success() {
-                eprintln!("Failed to copy file: {}", String::from_utf8_lossy(&output.stderr));
-            }
+/// Keeps a queued client informed about its position until a slot frees up.
+///
+/// Requests sent while waiting are replayed once admitted. Returns `None` if
+/// the client left the queue.
+async fn wait_for_slot(conn: &mut Connection, mut ticket: QueueTicket) -> Option<SessionGuard> {
+    let mut early_requests = Vec::new();
+    let mut last_position = 0;
+
+    let guard = loop {
+        if let Some(guard) = ticket.try_admit() {
+            break guard;
         }
-        Err(_err) => {
-            eprintln!("ERR_PLAYGROUND_CP_EXE");
+
+        let (position, estimated_wait) = ticket.position();
+        if position != last_position {
+            last_position = position;
+            let _ = conn.send(ServerMessage::Queued {
+                position: position as u32,
+                estimated_wait_secs: estimated_wait.as_secs(),
+            }).await;
+        }
+
+        select! {
+            _ = ticket.changed() => {},
+            incoming = conn.recv() => match incoming {
+                Incoming::Message(ClientMessage::Exit) | Incoming::Closed => return None,
+                Incoming::ShuttingDown => {
+                    let _ = conn.send(ServerMessage::exit(SHUTDOWN_REASON)).await;
+                    return None;
+                },
+                Incoming::Message(msg) => early_requests.push(msg),
+                Incoming::Corrupted(reason) => {
+                    let _ = conn.send(ServerMessage::RequestCorrupted { reason }).await;
+                },
+            },
         }
+    };
+
+    let _ = conn.send(ServerMessage::Admitted).await;
+    for msg in early_requests {
+        conn.defer(msg);
     }
 
-    //execute .exe
-    if let Ok(async_runtime) = tokio::runtime::Runtime::new() {
-        if let Err(err) = async_runtime.block_on(async {//block_on = wait until finish of execution
-            let child = tokio_command::new("docker")
-                .args(["exec", "-i", RUNNER_CONTAINER_NAME, "sh", "-c", &format!("./{id}")])
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn();
-
-            let mut shutdown: bool = false;
-            let mut server_res: VecDeque<JsonInfo> = VecDeque::new();
-
-            if child.is_err() {
-                return Err("ERR_PLAYGROUND_RUN_LAUNCH_EXEC");
-            }
+    Some(guard)
+}
 
-            let mut child = child.unwrap();
+/// Listener a client connected to.
+#[derive(Debug, Clone, Copy)]
+enum Gateway {
+    /// Length-prefixed frames, for the desktop app.
+    Tcp,
+    /// For the web app, in the browser.
+    WebSocket,
+}
 
-            let stdin = child.stdin.take();
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
+impl Gateway {
+    fn as_str(self) -> &'static str {
+        match self {
+            Gateway::Tcp => "tcp",
+            Gateway::WebSocket => "websocket",
+        }
+    }
+}
 
-            if stdin.is_none() || stdout.is_none() || stderr.is_none() {
-                return Err("ERR_PLAYGROUND_RUN_TAKE_STDIOS".into());
-            }
+/// The transport of a client accepted by `gateway`, once its handshakes
+/// (TLS with `tls`, then WebSocket) are over.
+async fn open_transport(stream: TcpStream, gateway: Gateway, tls: Option<&TlsAcceptor>) -> io::Result<Box<dyn Transport>> {
+    let stream = tls::accept(tls, stream).await?;
 
-            let mut stderr = stderr.unwrap();
-            let mut stdout = stdout.unwrap();
-            let mut stdin = stdin.unwrap();
-
-            while !shutdown {
-                //READ STDERR
-                loop {
-                    match consume_ready(&mut stderr).await {
-                        Ok(bytes) if !bytes.is_empty() => {
-                            let s = String::from_utf8_lossy(&bytes);
-
-                            if s == "EOF" {
-                                println!("stderr pipe reached EOF");
-                                shutdown = true;
-                                break;
-                            }
-
-                            println!("stderr: {s}");
-                            server_res.push_back(JsonInfo::from("stderr", &s));
-                        }
-                        Ok(_) => {//no output available -> skip
-                            break;
-                        }
-                        Err(e) => {
-                            eprintln!("Error while reading stderr: {}", e);
-                            server_res.push_back(JsonInfo::from("error", &e.to_string()));
-                            shutdown = true;
-                            break;
-                        }
-                    }
-                }
+    Ok(match gateway {
+        Gateway::Tcp => Box::new(Framed::new(stream, ServerCodec::new())),
+        Gateway::WebSocket => Box::new(websocket::accept(stream).await?),
+    })
+}
 
-                //READ STDOUT
-                loop {
-                    match consume_ready(&mut stdout).await {
-                        Ok(bytes) if !bytes.is_empty() => {
-                            let s = String::from_utf8_lossy(&bytes);
-
-                            if s == "EOF" {
-                                println!("stdout pipe reached EOF");
-                                shutdown = true;
-                                break;
-                            }
-
-                            println!("stdout: {s}");
-                            server_res.push_back(JsonInfo::from("stdout", &s));
-                        }
-                        Ok(_) => {//no output available -> skip
-                            break;
-                        }
-                        Err(e) => {
-                            eprintln!("Error while reading stdout: {}", e);
-                            server_res.push_back(JsonInfo::from("error", &e.to_string()));
-                            shutdown = true;
-                            break;
-                        }
-                    }
-                }
+/// Serves the clients of both gateways (over TLS with `tls`) until `shutdown_signal` resolves,
+/// then waits for the sessions to end: requests in progress get the grace
+/// period of `config` to finish, and every session cleans the backend.
+pub async fn spawn_tcp_server(
+    config: &ServerConfig,
+    backend: Arc<dyn ExecutionBackend>,
+    snippets: Arc<SnippetStore>,
+    auth: Arc<Authenticator>,
+    quotas: Arc<Quotas>,
+    tls: Option<TlsAcceptor>,
+    shutdown_signal: impl Future<Output = ()>,
+) -> Result<(), DynError> {
+    //init general
+    let registry = SessionRegistry::new(config.max_clients, config.max_waiting_clients, config.max_sessions_per_user);
+    let shutdown = Shutdown::new(config.shutdown_grace_period());
+    let sessions = TaskTracker::new();
+
+    //init metrics (the playground works without them)
+    let metrics_address = config.metrics_address;
+    let metrics_server = metrics::serve(metrics_address, Arc::clone(&registry));
+    tokio::spawn(async move {
+        if let Err(e) = metrics_server.await {
+            error!("Metrics unavailable, fail to bind to {metrics_address}: {e}");
+        }
+    });
 
-                //READ STREAM
-                loop {
-                    match read_json_info(&mut stream) {
-                        Ok(mut client_res) => {
-                            if client_res.header == "exit" {
-                                shutdown = true;
-                                break;
-
-                            } else if client_res.header == "input" {
-                                //important since no input is processed if there is not a '\n'
-                                if !client_res.body.ends_with('\n') {
-                                    client_res.body.push('\n');                                    
-                                }
-
-                                if let Err(_err)  = stdin.write_all(&client_res.body.as_bytes()).await {
-                                    eprintln!("ERR_PLAYGROUND_FORWARD_STDIN");
-                                    server_res.push_back(
-                                        JsonInfo::from("error", "ERR_PLAYGROUND_FORWARD_STDIN"
-                                    ));
-                                    shutdown = true;
-                                    break;
-
-                                }
-                            }
-                        },
+    //init server
+    let Ok(server) = TcpListener::bind(config.address).await else {
+        return Err(format!("Fail to bind to adress {}!", config.address).into());
+    };
+    let Ok(websocket_server) = TcpListener::bind(config.websocket_address).await else {
+        return Err(format!("Fail to bind to adress {}!", config.websocket_address).into());
+    };
+
+    //loop service
+    let security = if tls.is_some() { "TLS" } else { "plain" };
+    info!(
+        "Server listening on {}, and on {} for WebSocket ({security}, {} backend) ...",
+        config.address, config.websocket_address, backend.name()
+    );
+    tokio::pin!(shutdown_signal);
+    loop {
+        let (accepted, gateway) = select! {
+            accepted = server.accept() => (accepted, Gateway::Tcp),
+            accepted = websocket_server.accept() => (accepted, Gateway::WebSocket),
+            _ = &mut shutdown_signal => break,
+        };
+
+        match accepted {
+            Ok((stream, addr)) => {
+                let shutdown = shutdown.clone();
+                let tls = tls.clone();
+                let id = Uuid::new_v4();
+                let backend = Arc::clone(&backend);
+                let snippets = Arc::clone(&snippets);
+                let auth = Arc::clone(&auth);
+                let quotas = Arc::clone(&quotas);
+                let registry = Arc::clone(&registry);
+
+                METRICS.session_started();
+
+                //every event of the session carries its id, and the login once authenticated
+                let session = info_span!("session", %id, user = field::Empty);
+                session.in_scope(|| info!(
+                    gateway = gateway.as_str(),
+                    active = registry.active_sessions(),
+                    waiting = registry.waiting_sessions(),
+                    "Client connected"
+                ));
+
+                sessions.spawn(async move {
+                    let transport = match open_transport(stream, gateway, tls.as_ref()).await {
+                        Ok(transport) => transport,
                         Err(e) => {
-                            if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                                match e.kind() {
-                                    ErrorKind::WouldBlock => {//non-block error
-                                        break;
-                                    },
-                                    _ => {//severe error
-                                        eprintln!("Server error while listening: {e}");
-                                        server_res.push_back(
-                                            JsonInfo::from("error", "Server error while listening"
-                                        ));
-                                        shutdown = true;
-                                        break;
-
-                                    },
-                                }
-                            } else {//non severe error
-                                println!("{e}");
-                                server_res.push_back(JsonInfo::from("request_corrupted", ""));   
-                            }
+                            warn!(error = %e, "handshake failed");
+                            return;
                         },
-                    }
-                }
-                
-                //WRITE TO CLIENT
-                while let Some(res) = server_res.pop_front() {//foreach JsonInfo needed to be sent
-                    match write_json_info(&mut stream, res) {
-                        Ok(_ok) => {
-                            // smth
+                    };
+                    let mut conn = Connection::new(transport, addr, shutdown);
+
+                    //check client in server, once it said hello
+                    let Ok(account) = greet(&mut conn, &auth, HELLO_TIMEOUT).await else {
+                        return;
+                    };
+                    let _guard = match registry.join(id, UserKey::of(account.as_ref(), addr.ip())) {//slot is released when the guard is dropped
+                        Admission::Admitted(guard) => guard,
+                        Admission::Queued(ticket) => match wait_for_slot(&mut conn, ticket).await {
+                            Some(guard) => guard,
+                            None => return,
                         },
-                        Err(e) => {
-                            if let Some(e) = e.downcast_ref::<io::Error>() {//get original error
-                                match e.kind() {
-                                    ErrorKind::WouldBlock => {//non-block error
-                                        // smth
-                                    },
-                                    _ => {//severe error
-                                        return Err("Server error while writing: {e}".into());
-                                    },
-                                }
-                            } else {//non severe error
-                                eprintln!("{e}");
-                            }
+                        Admission::Rejected => {
+                            METRICS.failed(Failure::Rejected);
+                            warn!("Max clients number reached, refusing further connections");
+                            let _res = conn.send(
+                                ServerMessage::exit("Max clients number reached, refusing further connections")
+                            ).await;
+                            return;
                         },
-                    }
-                }
+                        Admission::TooManySessions => {
+                            METRICS.failed(Failure::Rejected);
+                            warn!("Max sessions of the user reached, refusing the connection");
+                            let _res = conn.send(
+                                ServerMessage::exit("You already have too many sessions open, close one first")
+                            ).await;
+                            return;
+                        },
+                    };
 
-                tokio::time::sleep(Duration::from_millis(200)).await;
+                    handle_client(conn, id, backend, snippets, quotas, account).await;
+                }.instrument(session));
             }
-
-            // Wait for the child to exit
-            if let Err(_err) = child.wait().await {
-                return Err("ERR_PLAYGROUND_RUN_CHILD_WAIT".into());
-            } else {
-                return Ok(());
+            Err(e) => {
+                error!("Failed to estabilish connection: {e}");
             }
-        }) {
-            return Err(err.into());
         }
-    } else {
-        return Err("ERR_PLAYGROUND_RUN_ASYNC_RT".into());
     }
 
-    Ok("Ok".to_string())
-}
+    //stop accepting, then let the sessions end
+    drop(server);
+    drop(websocket_server);
+    info!(
+        sessions = sessions.len(),
+        "Shutting down: {}s left to the requests in progress", shutdown.grace_period().as_secs()
+    );
+    shutdown.begin();
+    sessions.close();
 
-pub fn docker_clean_run(id: Uuid) -> Result<String, Box<dyn std::error::Error>> {
-    //WARNING: may not clear everything on container; be sure to run a deep cleaning script every run
-    //rm exe
-    docker_rm_file(RUNNER_CONTAINER_NAME, &format!("{id}"))?;
-    docker_rm_file(RUNNER_CONTAINER_NAME, &format!("../shared_folder/{id}"))?;
+    if timeout(shutdown.grace_period() + CLEANUP_TIMEOUT, sessions.wait()).await.is_err() {
+        warn!(sessions = sessions.len(), "Sessions still running at exit, their files may be left in the backend");
+    }
 
-    Ok("Ok".to_string())
+    Ok(())
 }
 
-pub fn docker_rm_file(container_name: &str, file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let status = Command::new("docker")
-        .args(["exec", container_name, "rm", "-f", file_path])
-        .status();
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::duplex;
+
+    use super::*;
+    use crate::{
+        backend::local::LocalBackend,
+        config::{AuthConfig, QuotasConfig},
+    };
+
+    /// A server connection, and the client end of it.
+    fn connect() -> (Connection, Framed<tokio::io::DuplexStream, ClientCodec>) {
+        let (server, client) = duplex(MAX_FRAME_LEN);
+        let conn = Connection::new(
+            Box::new(Framed::new(server, ServerCodec::new())),
+            "127.0.0.1:4242".parse().unwrap(),
+            Shutdown::new(Duration::ZERO),
+        );
+        (conn, Framed::new(client, ClientCodec::new()))
+    }
 
-    match status {
-        Ok(status) => {
-            match status.success() {
-                true => Ok(format!("file '{container_name}:{file_path}' removed succesfully")),
-                false => Err(format!("failed to remove file '{container_name}:{file_path}'").into())
-            }
-        }
-        Err(_err) => {
-            Err(_err.into())
-        }
+    #[tokio::test]
+    async fn clients_are_greeted() {
+        let auth = Authenticator::new(&AuthConfig::default()).unwrap();
+        let (mut conn, mut client) = connect();
+
+        client.send(ClientMessage::hello()).await.unwrap();
+        assert_eq!(greet(&mut conn, &auth, HELLO_TIMEOUT).await, Ok(None));
+        assert_eq!(client.next().await.unwrap().unwrap().unwrap(), ServerMessage::welcome(None));
     }
-}
 
-pub fn spawn_tcp_server() -> Result<(), Box<dyn std::error::Error>> {
-    //init general
-    let client_accepted: Arc<Mutex<u8>> = Arc::new(Mutex::new(0));
+    #[tokio::test]
+    async fn older_clients_are_refused() {
+        let auth = Authenticator::new(&AuthConfig::default()).unwrap();
+        let (mut conn, mut client) = connect();
 
-    //init server
-    let server = TcpListener::bind(SERVER_ADDRESS);
-    if server.is_err() {
-        return Err("Fail to bind to adress!".into());
+        client.send(ClientMessage::Hello { protocol_version: PROTOCOL_VERSION - 1, token: None }).await.unwrap();
+        assert_eq!(PROTOCOL_VERSION, 3);
+        assert_eq!(greet(&mut conn, &auth, HELLO_TIMEOUT).await, Err(()));
+
+        assert!(matches!(client.next().await, Some(Ok(Ok(ServerMessage::Error { .. })))));
+        assert_eq!(client.next().await.unwrap().unwrap().unwrap(), ServerMessage::exit("protocol version mismatch"));
     }
-    let server = server.unwrap();
 
-    //loop service
-    println!("Server listening on {SERVER_ADDRESS} ...");
-    for stream in server.incoming() {
-        match stream {
-            Ok(mut stream) => {
-                let client_accepted = Arc::clone(&client_accepted);
-                
-                //check client in server
-                let mut mutex_client_accepted = client_accepted.lock().unwrap();
-
-                if *mutex_client_accepted < MAX_CLIENTS {
-                    //client++ 
-                    *mutex_client_accepted = *mutex_client_accepted + 1;
-                    drop(mutex_client_accepted);
-
-                    std::thread::spawn(move || handle_client(stream, Uuid::new_v4()));
-
-                    //client--
-                    let mut mutex_client_accepted = client_accepted.lock().unwrap();
-                    *mutex_client_accepted = *mutex_client_accepted - 1;
-                    drop(mutex_client_accepted);
-
-                } else {
-                    eprintln!("Max clients number reached, refusing further connections");
-                    let _res = write_json_info(
-                        &mut stream,
-                        JsonInfo::from(
-                            "exit",
-                            "Max clients number reached, refusing further connections"
-                        )
-                    );
-                    
-                    drop(stream);
-                }
+    #[tokio::test]
+    async fn silent_clients_are_disconnected() {
+        let auth = Authenticator::new(&AuthConfig::default()).unwrap();
+        let (mut conn, mut client) = connect();
 
-            }
-            Err(e) => {
-                eprintln!("Failed to estabilish connection: {e}");
-            }
-        }
+        assert_eq!(greet(&mut conn, &auth, Duration::from_millis(50)).await, Err(()));
+        drop(conn);
+
+        assert!(matches!(client.next().await, Some(Ok(Ok(ServerMessage::Exit { .. })))));
+        assert!(client.next().await.is_none());
     }
 
-    println!("Server is shutting down!");
-    Ok(())
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn sessions_keep_their_account() {
+        let (conn, mut client) = connect();
+        let session = tokio::spawn(handle_client(
+            conn,
+            Uuid::new_v4(),
+            Arc::new(LocalBackend::new(std::env::temp_dir().join("rust_playground_tests"))),
+            Arc::new(SnippetStore::new(std::env::temp_dir().join("rust_playground_snippets_tests"), u64::MAX)),
+            Quotas::new(&QuotasConfig::default()),
+            None,
+        ));
+
+        client.send(ClientMessage::hello_with_token("someone.else")).await.unwrap();
+        client.send(ClientMessage::Exit).await.unwrap();
+
+        assert!(matches!(client.next().await, Some(Ok(Ok(ServerMessage::Error { .. })))));
+        session.await.unwrap();
+        assert!(client.next().await.is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+
+/// Shared by every session: tells them the server is going down, then that
+/// the grace period given to the requests in progress is over.
+#[derive(Clone)]
+pub struct Shutdown {
+    started: CancellationToken,
+    expired: CancellationToken,
+    grace_period: Duration,
+}
+
+impl Shutdown {
+    pub fn new(grace_period: Duration) -> Self {
+        Shutdown { started: CancellationToken::new(), expired: CancellationToken::new(), grace_period }
+    }
+
+    /// Starts the shutdown; the grace period expires by itself.
+    pub fn begin(&self) {
+        if self.started.is_cancelled() {
+            return;
+        }
+        self.started.cancel();
+
+        let expired = self.expired.clone();
+        let grace_period = self.grace_period;
+        tokio::spawn(async move {
+            sleep(grace_period).await;
+            expired.cancel();
+        });
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started.is_cancelled()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expired.is_cancelled()
+    }
+
+    /// Resolves once the shutdown began.
+    pub async fn started(&self) {
+        self.started.cancelled().await
+    }
+
+    /// Resolves once the grace period is over.
+    pub async fn expired(&self) {
+        self.expired.cancelled().await
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+}
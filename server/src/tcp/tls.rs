@@ -0,0 +1,137 @@
+use std::{io, path::Path, sync::Arc, time::Duration};
+
+use tokio::{net::TcpStream, time::timeout};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use crate::{
+    config::{ConfigError, TlsConfig},
+    tcp::connection::ClientStream,
+};
+
+/// How long a client has to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The acceptor of the certificate and key named by `config`, or `None` to
+/// serve plain TCP.
+pub fn acceptor(config: &TlsConfig) -> Result<Option<TlsAcceptor>, ConfigError> {
+    let (Some(cert_file), Some(key_file)) = (&config.cert_file, &config.key_file) else {
+        return Ok(None);//checked by Config::validate
+    };
+    let invalid = |key, reason: String| ConfigError::Invalid { key, reason };
+
+    let certs = CertificateDer::pem_slice_iter(&read(cert_file)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid("tls.cert_file", e.to_string()))?;
+    if certs.is_empty() {
+        return Err(invalid("tls.cert_file", "no certificate in the file".to_string()));
+    }
+    let key = PrivateKeyDer::from_pem_slice(&read(key_file)?).map_err(|e| invalid("tls.key_file", e.to_string()))?;
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| invalid("tls.key_file", e.to_string()))?;
+
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, ConfigError> {
+    std::fs::read(path).map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })
+}
+
+/// `stream` as is, or wrapped in TLS by `acceptor` once the handshake is over.
+pub async fn accept(acceptor: Option<&TlsAcceptor>, stream: TcpStream) -> io::Result<Box<dyn ClientStream>> {
+    let Some(acceptor) = acceptor else {
+        return Ok(Box::new(stream));
+    };
+
+    match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(stream) => Ok(Box::new(stream?)),
+        Err(_elapsed) => Err(io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use playground_protocol::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+    use tokio_util::codec::Framed;
+
+    use super::*;
+    use crate::tcp::{
+        connection::{Connection, Incoming},
+        shutdown::Shutdown,
+    };
+
+    fn connector(roots: RootCertStore) -> TlsConnector {
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        TlsConnector::from(Arc::new(config))
+    }
+
+    #[tokio::test]
+    async fn clients_talk_over_tls() {
+        //self-signed certificate, trusted by the client as its CA
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = std::env::temp_dir().join("rust_playground_tls_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+
+        let config = TlsConfig { cert_file: Some(dir.join("cert.pem")), key_file: Some(dir.join("key.pem")) };
+        assert!(acceptor(&TlsConfig::default()).unwrap().is_none());
+        let acceptor = acceptor(&config).unwrap().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (stream, peer) = listener.accept().await.unwrap();
+                let Ok(stream) = accept(Some(&acceptor), stream).await else {
+                    continue;//the untrusting client
+                };
+
+                let transport = Box::new(Framed::new(stream, ServerCodec::new()));
+                let mut conn = Connection::new(transport, peer, Shutdown::new(Duration::ZERO));
+                assert!(matches!(conn.recv().await, Incoming::Message(ClientMessage::Hello { .. })));
+                conn.send(ServerMessage::welcome(None)).await.unwrap();
+            }
+        });
+
+        //a client without the CA refuses the certificate
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let name = ServerName::try_from("localhost").unwrap();
+        assert!(connector(RootCertStore::empty()).connect(name.clone(), stream).await.is_err());
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector(roots).connect(name, stream).await.unwrap();
+
+        stream.write_all(&encode_message(&ClientMessage::hello()).unwrap()).await.unwrap();
+        let len = stream.read_u32().await.unwrap();
+        let mut frame = len.to_be_bytes().to_vec();
+        frame.resize(4 + len as usize, 0);
+        stream.read_exact(&mut frame[4..]).await.unwrap();
+        assert_eq!(decode_message::<ServerMessage>(&mut frame).unwrap(), Some(ServerMessage::welcome(None)));
+
+        server.await.unwrap();
+    }
+}
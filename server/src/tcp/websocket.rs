@@ -0,0 +1,109 @@
+use std::{future, io, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use tokio::time::timeout;
+use tokio_tungstenite::{
+    accept_async_with_config,
+    tungstenite::{self, error::ProtocolError as WsProtocolError, protocol::WebSocketConfig, Message},
+};
+
+use playground_protocol::*;
+
+use crate::tcp::connection::{ClientStream, Transport};
+
+/// How long a client has to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepts the WebSocket handshake on `stream` (plain or TLS), for the
+/// clients that can't open a socket, like the browser.
+///
+/// Every WebSocket message (text or binary) is one protocol message, as the
+/// JSON of a frame without its length prefix.
+pub async fn accept(stream: Box<dyn ClientStream>) -> io::Result<impl Transport> {
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_FRAME_LEN))
+        .max_frame_size(Some(MAX_FRAME_LEN));
+
+    let socket = match timeout(HANDSHAKE_TIMEOUT, accept_async_with_config(stream, Some(config))).await {
+        Ok(socket) => socket.map_err(into_io)?,
+        Err(_elapsed) => return Err(io::Error::new(io::ErrorKind::TimedOut, "WebSocket handshake timed out")),
+    };
+
+    Ok(socket
+        .sink_map_err(|e| ProtocolError::Io(into_io(e)))
+        .with(|msg: ServerMessage| future::ready(encode(&msg)))
+        .filter_map(|msg| future::ready(decode(msg))))
+}
+
+fn encode(msg: &ServerMessage) -> Result<Message, ProtocolError> {
+    let json = serde_json::to_string(msg)?;
+    if json.len() > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge { len: json.len(), max: MAX_FRAME_LEN });
+    }
+    Ok(Message::text(json))
+}
+
+/// The client message in `msg`, if it carries one (pings and closes don't).
+fn decode(msg: Result<Message, tungstenite::Error>) -> Option<Result<Result<ClientMessage, ProtocolError>, ProtocolError>> {
+    match msg {
+        Ok(Message::Text(json)) => Some(Ok(serde_json::from_str(&json).map_err(ProtocolError::from))),
+        Ok(Message::Binary(json)) => Some(Ok(serde_json::from_slice(&json).map_err(ProtocolError::from))),
+        Ok(Message::Ping(_) | Message::Pong(_) | Message::Close(_) | Message::Frame(_)) => None,
+        Err(e) => Some(Err(ProtocolError::Io(into_io(e)))),
+    }
+}
+
+/// The client leaving without the closing handshake is just a disconnection.
+fn into_io(e: tungstenite::Error) -> io::Error {
+    match e {
+        tungstenite::Error::Io(e) => e,
+        tungstenite::Error::ConnectionClosed
+        | tungstenite::Error::AlreadyClosed
+        | tungstenite::Error::Protocol(WsProtocolError::ResetWithoutClosingHandshake) => {
+            io::ErrorKind::UnexpectedEof.into()
+        },
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::client_async;
+
+    use super::*;
+    use crate::tcp::{
+        connection::{Connection, Incoming},
+        shutdown::Shutdown,
+    };
+
+    #[tokio::test]
+    async fn clients_talk_over_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, peer) = listener.accept().await.unwrap();
+            let transport = accept(Box::new(stream)).await.unwrap();
+            let mut conn = Connection::new(Box::new(transport), peer, Shutdown::new(Duration::ZERO));
+
+            assert!(matches!(conn.recv().await, Incoming::Message(ClientMessage::Hello { .. })));
+            conn.send(ServerMessage::welcome(None)).await.unwrap();
+            assert!(matches!(conn.recv().await, Incoming::Corrupted(_)));
+            assert!(matches!(conn.recv().await, Incoming::Closed));
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut socket, _response) = client_async(format!("ws://{addr}/"), stream).await.unwrap();
+
+        socket.send(Message::text(serde_json::to_string(&ClientMessage::hello()).unwrap())).await.unwrap();
+        let Some(Ok(Message::Text(json))) = socket.next().await else {
+            panic!("no welcome");
+        };
+        assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), ServerMessage::welcome(None));
+
+        socket.send(Message::text("{\"type\": \"launch_missiles\"}")).await.unwrap();
+        socket.close(None).await.unwrap();
+
+        server.await.unwrap();
+    }
+}